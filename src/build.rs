@@ -14,6 +14,7 @@ use crate::{
     cache::BuiltOCACache,
     dependency_graph::{parse_node, GraphError, MutableGraph, Node, NodeParsingError},
     error::CliError,
+    history::RefnHistory,
     publish_oca_file_for,
 };
 use oca_rs::EncodeBundle;
@@ -93,11 +94,13 @@ pub fn changed_files<'a>(
         .collect()
 }
 
-/// Build node. If caches provided, save change there. Returns SAID of built ocafile, and its contents.
+/// Build node. If caches provided, save change there. If history provided,
+/// record new SAID of refn. Returns SAID of built ocafile, and its contents.
 pub fn build(
     facade: Arc<Mutex<Facade>>,
     node: &Node,
     said_cache: Option<&BuiltOCACache>,
+    history: Option<&RefnHistory>,
 ) -> Result<Option<(SelfAddressingIdentifier, String)>, CliError> {
     info!("Building: {:?}", node);
     let path = &node.path;
@@ -117,22 +120,13 @@ pub fn build(
             if let Some(said_cache) = said_cache {
                 said_cache.insert(&unparsed_file, said.clone()).unwrap();
             };
-            let refs = {
-                let facade_locked = facade.lock().unwrap();
-                facade_locked.fetch_all_refs().unwrap()
-            };
-            let schema_name = refs.iter().find(|&(_, v)| *v == said.to_string());
-            if let Some((refs, _)) = schema_name {
-                println!(
-                    "OCA bundle created in local repository with SAID: {} and name: {}",
-                    &said, refs
-                );
-            } else {
-                println!(
-                    "OCA bundle created in local repository with SAID: {:?}",
-                    &said
-                );
+            if let Some(history) = history {
+                history.record_build(&node.refn, said, path, &unparsed_file)?;
             };
+            println!(
+                "OCA bundle created in local repository with SAID: {} and name: {}",
+                &said, node.refn
+            );
             Ok(Some((said.clone(), unparsed_file)))
         }
         BundleElement::Transformation(transformation_file) => {
@@ -185,12 +179,19 @@ pub fn rebuild(
     directory: &Path,
     facade: Arc<Mutex<Facade>>,
     nodes: &[Node],
+    history: Option<&RefnHistory>,
 ) -> Result<(Vec<Node>, BuiltOCACache), CliError> {
     let (cache, nodes_to_build) = {
         
         let mut cache_path = directory.to_path_buf();
         cache_path.push(".oca-bin");
         let cache = BuiltOCACache::new(&cache_path).unwrap();
+        // Bundles could be deleted since they were built, e.g. by `oca gc`.
+        // Their ocafiles need to be built again.
+        {
+            let facade = facade.lock().unwrap();
+            cache.retain(|said| facade.get_oca_bundle(said.clone(), false).is_ok())?;
+        }
 
         match detect_changes(&nodes, &cache) {
             Ok(nodes_to_update) => {
@@ -221,6 +222,7 @@ pub fn rebuild(
             facade.clone(),
             node,
             Some(&cache),
+            history,
             // Some(&cached_digests),
         )?;
     }
@@ -422,3 +424,76 @@ pub fn test_changed_files() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+pub fn test_build_records_history_of_node_name() -> anyhow::Result<()> {
+    use tempdir::TempDir;
+
+    let tmp_dir = TempDir::new("build_history")?;
+    let facade = Arc::new(Mutex::new(crate::get_oca_facade(
+        tmp_dir.path().join("repo"),
+    )));
+    let history = RefnHistory::new(tmp_dir.path().join("history"))?;
+
+    // Both names point to the same SAID.
+    let mut saids = vec![];
+    for refn in ["first", "alias"] {
+        let path = tmp_dir.path().join(format!("{}.ocafile", refn));
+        fs::write(&path, format!("-- name={}\nADD ATTRIBUTE name=Text", refn))?;
+        let node = Node {
+            refn: refn.to_string(),
+            path,
+            said: None,
+        };
+        let (said, _) = build(facade.clone(), &node, None, Some(&history))?.unwrap();
+        saids.push(said);
+    }
+    assert_eq!(saids[0], saids[1]);
+
+    for refn in ["first", "alias"] {
+        let entries = history.entries(refn)?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].path.as_deref(),
+            Some(tmp_dir.path().join(format!("{}.ocafile", refn)).as_path())
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn test_rebuild_after_gc() -> anyhow::Result<()> {
+    use tempdir::TempDir;
+
+    let tmp_dir = TempDir::new("rebuild_gc")?;
+    let repository = tmp_dir.path().join("repository");
+    let directory = tmp_dir.path().join("ocafiles");
+    fs::create_dir_all(&directory)?;
+    let path = directory.join("first.ocafile");
+    let first = "-- name=first\nADD ATTRIBUTE name=Text\n";
+    let edited = "-- name=first\nADD ATTRIBUTE name=Text age=Numeric\n";
+
+    let build_file = |ocafile: &str| -> anyhow::Result<Vec<Node>> {
+        fs::write(&path, ocafile)?;
+        let (node, _) = parse_node(&path)?;
+        let facade = Arc::new(Mutex::new(crate::get_oca_facade(repository.clone())));
+        let (rebuilt, _) = rebuild(&directory, facade, &[node], None)?;
+        Ok(rebuilt)
+    };
+    assert_eq!(build_file(first)?.len(), 1);
+    assert_eq!(build_file(edited)?.len(), 1);
+
+    let garbage = crate::gc::find_garbage(&repository, None, &[])?;
+    assert_eq!(garbage.saids.len(), 1);
+    crate::gc::collect(&repository, &garbage)?;
+
+    // Reverted file was built before, but its bundle is gone.
+    assert_eq!(build_file(first)?.len(), 1);
+    let facade = crate::get_oca_facade(repository.clone());
+    let said: SelfAddressingIdentifier = facade.fetch_all_refs().unwrap()["first"].parse()?;
+    assert_eq!(said.to_string(), garbage.saids[0]);
+    assert!(facade.get_oca_bundle(said, false).is_ok());
+
+    Ok(())
+}
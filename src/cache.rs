@@ -31,9 +31,28 @@ impl BuiltOCACache {
         let hash = compute_hash(oca_file.trim());
         Ok(bucket.get(&hash)?.map(|el| el.0))
     }
+
+    /// Removes entries of SAIDs for which `keep` returns false, e.g. bundles
+    /// deleted from local repository.
+    pub fn retain(
+        &self,
+        keep: impl Fn(&SelfAddressingIdentifier) -> bool,
+    ) -> Result<(), kv::Error> {
+        let bucket: Bucket<String, Json<SelfAddressingIdentifier>> =
+            self.store.bucket(Some("already_built"))?;
+        for item in bucket.iter() {
+            let item = item?;
+            let said: Json<SelfAddressingIdentifier> = item.value()?;
+            if !keep(&said.0) {
+                bucket.remove(&item.key()?)?;
+            }
+        }
+        bucket.flush()?;
+        Ok(())
+    }
 }
 
-fn compute_hash(content: &str) -> String {
+pub fn compute_hash(content: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(content);
     let result = hasher.finalize();
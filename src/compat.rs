@@ -0,0 +1,259 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+use oca_rs::Facade;
+use said::SelfAddressingIdentifier;
+use serde_json::Value;
+
+use crate::{
+    dependency_graph::Node,
+    diff::{diff, BundleDiff, Change},
+    error::CliError,
+    history::RefnHistory,
+};
+
+/// Kind of change between two versions of OCA bundle, ordered from the least
+/// to the most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Compatibility {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Display for Compatibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Compatibility::Patch => write!(f, "patch"),
+            Compatibility::Minor => write!(f, "minor"),
+            Compatibility::Major => write!(f, "major (breaking)"),
+        }
+    }
+}
+
+/// Overlays with human readable texts only.
+const TEXT_OVERLAYS: &[&str] = &["label", "information", "meta"];
+
+/// Classifies single change:
+/// * removed or retyped attributes, removed overlays and entry codes and
+///   attributes becoming mandatory are breaking,
+/// * added attributes, overlays and translations are minor,
+/// * edits of label, information and meta texts are patch.
+pub fn classify_change(change: &Change) -> Compatibility {
+    match change {
+        Change::AttributeRemoved { .. }
+        | Change::AttributeTypeChanged { .. }
+        | Change::OverlayRemoved { .. } => Compatibility::Major,
+        Change::AttributeAdded { .. }
+        | Change::FlaggedAdded { .. }
+        | Change::FlaggedRemoved { .. }
+        | Change::ClassificationChanged { .. }
+        | Change::OverlayAdded { .. } => Compatibility::Minor,
+        Change::OverlayChanged {
+            overlay, from, to, ..
+        } => match (overlay.as_str(), from, to) {
+            (overlay, Some(_), Some(_)) if TEXT_OVERLAYS.contains(&overlay) => Compatibility::Patch,
+            (overlay, _, _) if TEXT_OVERLAYS.contains(&overlay) => Compatibility::Minor,
+            ("conformance", _, Some(Value::String(to))) if to == "M" => Compatibility::Major,
+            ("conformance", _, _) => Compatibility::Minor,
+            ("entry_code", from, to) => {
+                let codes = |value: &Option<Value>| -> Vec<Value> {
+                    match value {
+                        Some(Value::Array(codes)) => codes.clone(),
+                        Some(value) => vec![value.clone()],
+                        None => vec![],
+                    }
+                };
+                let new_codes = codes(to);
+                if codes(from).iter().all(|code| new_codes.contains(code)) {
+                    Compatibility::Minor
+                } else {
+                    Compatibility::Major
+                }
+            }
+            // Changed constraint can reject data that was valid before.
+            (_, Some(_), Some(_)) => Compatibility::Major,
+            _ => Compatibility::Minor,
+        },
+    }
+}
+
+/// Returns the most severe change of bundle and bundles it references, or
+/// `None` if nothing changed.
+pub fn classify(diff: &BundleDiff) -> Option<Compatibility> {
+    diff.changes
+        .iter()
+        .map(classify_change)
+        .chain(diff.references.values().filter_map(classify))
+        .max()
+}
+
+/// Returns descriptions of breaking changes in diff.
+fn breaking_changes(diff: &BundleDiff, prefix: &str, out: &mut Vec<String>) {
+    for change in &diff.changes {
+        if classify_change(change) == Compatibility::Major {
+            out.push(format!("{}{}", prefix, change));
+        }
+    }
+    for (attribute, nested) in &diff.references {
+        breaking_changes(nested, &format!("{}{} -> ", prefix, attribute), out);
+    }
+}
+
+/// Compatibility of new SAID of refn with the previous one.
+#[derive(Debug)]
+pub struct CompatReport {
+    pub refn: String,
+    pub from: SelfAddressingIdentifier,
+    pub to: SelfAddressingIdentifier,
+    pub compatibility: Option<Compatibility>,
+    pub breaking: Vec<String>,
+    /// Next version, based on the highest version tag of refn.
+    pub suggested_version: Option<String>,
+}
+
+impl CompatReport {
+    pub fn print(&self) {
+        let compatibility = self
+            .compatibility
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| "no changes".to_string());
+        println!(
+            "{}: {} ({} -> {})",
+            self.refn, compatibility, self.from, self.to
+        );
+        for change in &self.breaking {
+            println!("    {}", change);
+        }
+        if let Some(version) = &self.suggested_version {
+            println!("    Suggested version: {}", version);
+        }
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.compatibility == Some(Compatibility::Major)
+    }
+}
+
+/// Compares SAIDs of built nodes with SAIDs their names pointed to before
+/// the build.
+pub fn check(
+    facade: &Facade,
+    previous: &HashMap<String, String>,
+    nodes: &[Node],
+    history: Option<&RefnHistory>,
+) -> Result<Vec<CompatReport>, CliError> {
+    let current = facade.fetch_all_refs().unwrap_or_default();
+    let mut reports = vec![];
+    for node in nodes {
+        let (from, to) = match (previous.get(&node.refn), current.get(&node.refn)) {
+            (Some(from), Some(to)) if from != to => (from.parse()?, to.parse()?),
+            _ => continue,
+        };
+        let diff = diff(facade, &from, &to)?;
+        let compatibility = classify(&diff);
+        let mut breaking = vec![];
+        breaking_changes(&diff, "", &mut breaking);
+        let suggested_version = match (history, compatibility) {
+            (Some(history), Some(compatibility)) => {
+                suggest_version(history.tags(&node.refn)?.keys(), compatibility)
+            }
+            _ => None,
+        };
+        reports.push(CompatReport {
+            refn: node.refn.clone(),
+            from,
+            to,
+            compatibility,
+            breaking,
+            suggested_version,
+        });
+    }
+    Ok(reports)
+}
+
+fn parse_version(tag: &str) -> Option<(u64, u64, u64)> {
+    let core = tag.strip_prefix('v').unwrap_or(tag);
+    let core = core.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    match (parts.next()??, parts.next()??, parts.next()??, parts.next()) {
+        (major, minor, patch, None) => Some((major, minor, patch)),
+        _ => None,
+    }
+}
+
+/// Returns version that follows the highest of version tags, e.g. `v2.0.0`
+/// after `v1.4.2` for breaking changes.
+pub fn suggest_version<'a>(
+    tags: impl IntoIterator<Item = &'a String>,
+    compatibility: Compatibility,
+) -> Option<String> {
+    let versions = tags
+        .into_iter()
+        .filter_map(|tag| parse_version(tag).map(|version| (version, tag.starts_with('v'))))
+        .collect::<BTreeMap<_, _>>();
+    let ((major, minor, patch), prefixed) = versions.into_iter().next_back()?;
+    let (major, minor, patch) = match compatibility {
+        // Before 1.0.0 breaking changes bump minor version.
+        Compatibility::Major if major == 0 => (0, minor + 1, 0),
+        Compatibility::Major => (major + 1, 0, 0),
+        Compatibility::Minor => (major, minor + 1, 0),
+        Compatibility::Patch => (major, minor, patch + 1),
+    };
+    let prefix = if prefixed { "v" } else { "" };
+    Some(format!("{}{}.{}.{}", prefix, major, minor, patch))
+}
+
+#[test]
+fn test_classify() -> anyhow::Result<()> {
+    use oca_rs::facade::bundle::BundleElement;
+
+    let tmp_dir = tempdir::TempDir::new("compat")?;
+    let mut facade = crate::get_oca_facade(tmp_dir.path().to_path_buf());
+    let mut build = |ocafile: &str| -> SelfAddressingIdentifier {
+        match facade.build_from_ocafile(ocafile.to_string()).unwrap() {
+            BundleElement::Mechanics(bundle) => bundle.said.unwrap(),
+            BundleElement::Transformation(_) => unreachable!(),
+        }
+    };
+    let base = build("ADD ATTRIBUTE name=Text age=Numeric\nADD LABEL en ATTRS name=\"Name\"");
+    let removed = build("ADD ATTRIBUTE name=Text\nADD LABEL en ATTRS name=\"Name\"");
+    let added = build(
+        "ADD ATTRIBUTE name=Text age=Numeric\nADD LABEL en ATTRS name=\"Name\"\nADD LABEL fr ATTRS name=\"Nom\"",
+    );
+    let edited =
+        build("ADD ATTRIBUTE name=Text age=Numeric\nADD LABEL en ATTRS name=\"Full name\"");
+
+    let compatibility = |to| classify(&diff(&facade, &base, to).unwrap());
+    assert_eq!(compatibility(&removed), Some(Compatibility::Major));
+    assert_eq!(compatibility(&added), Some(Compatibility::Minor));
+    assert_eq!(compatibility(&edited), Some(Compatibility::Patch));
+    assert_eq!(compatibility(&base), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_suggest_version() {
+    let tags = ["v1.2.3".to_string(), "v1.10.0".to_string()];
+    assert_eq!(
+        suggest_version(&tags, Compatibility::Major).as_deref(),
+        Some("v2.0.0")
+    );
+    assert_eq!(
+        suggest_version(&tags, Compatibility::Minor).as_deref(),
+        Some("v1.11.0")
+    );
+    assert_eq!(
+        suggest_version(&tags, Compatibility::Patch).as_deref(),
+        Some("v1.10.1")
+    );
+    let tags = ["0.3.1".to_string()];
+    assert_eq!(
+        suggest_version(&tags, Compatibility::Major).as_deref(),
+        Some("0.4.0")
+    );
+    assert_eq!(suggest_version(&[], Compatibility::Major), None);
+}
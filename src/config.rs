@@ -1,19 +1,51 @@
-use std::io::{self, Error, Write};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
 use std::{env, path::PathBuf};
-use std::{fs, process};
 
 use oca_rs::data_storage::{DataStorage, SledDataStorage, SledDataStorageConfig};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::lint::Severity;
 
 pub const OCA_CACHE_DB_DIR: &str = "oca_cache";
 pub const OCA_REPOSITORY_DIR: &str = "oca_repository";
 pub const OCA_INDEX_DIR: &str = "read_db";
+pub const OCA_HISTORY_DIR: &str = "oca_history";
 pub const OCA_DIR_NAME: &str = ".oca";
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+pub const SYSTEM_CONFIG_PATH: &str = "/etc/oca/config.toml";
+pub const OCA_CONFIG_ENV: &str = "OCA_CONFIG";
+pub const OCA_LOCAL_REPOSITORY_PATH_ENV: &str = "OCA_LOCAL_REPOSITORY_PATH";
+pub const OCA_REPOSITORY_URL_ENV: &str = "OCA_REPOSITORY_URL";
+pub const OCA_DEFAULT_REMOTE_ENV: &str = "OCA_DEFAULT_REMOTE";
+pub const OCA_PRESENTATION_ENDPOINT_ENV: &str = "OCA_PRESENTATION_ENDPOINT";
+/// Name under which `repository_url` is listed among named remotes.
+pub const DEFAULT_REMOTE_NAME: &str = "default";
+pub const DEFAULT_PRESENTATION_ENDPOINT: &str = "presentations";
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct Config {
     pub local_repository_path: PathBuf,
     pub repository_url: Option<String>,
+    /// Name of remote used when none is specified in command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_remote: Option<String>,
+    /// Endpoint of remote repository that accepts presentations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presentation_endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub remotes: BTreeMap<String, Remote>,
+    /// Severity of `oca lint` rules, by rule id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lint: BTreeMap<String, Severity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Remote {
+    pub url: String,
 }
 
 impl Config {
@@ -23,25 +55,56 @@ impl Config {
             ..Default::default()
         }
     }
-}
 
-pub fn read_config(path: &PathBuf) -> Result<Config, Error> {
-    let content = fs::read_to_string(path)?;
-    let config: Config = toml::from_str(&content)?;
-    Ok(config)
+    /// Returns all configured remotes as `(name, url)` pairs. Plain
+    /// `repository_url` is listed first, under `default` name.
+    pub fn remotes(&self) -> Vec<(String, String)> {
+        let mut remotes = vec![];
+        if let Some(url) = &self.repository_url {
+            if !self.remotes.contains_key(DEFAULT_REMOTE_NAME) {
+                remotes.push((DEFAULT_REMOTE_NAME.to_string(), url.clone()));
+            }
+        }
+        remotes.extend(
+            self.remotes
+                .iter()
+                .map(|(name, remote)| (name.clone(), remote.url.clone())),
+        );
+        remotes
+    }
+
+    pub fn presentation_endpoint(&self) -> &str {
+        self.presentation_endpoint
+            .as_deref()
+            .unwrap_or(DEFAULT_PRESENTATION_ENDPOINT)
+    }
+
+    /// Returns name of remote that should be used when none is specified.
+    pub fn default_remote_name(&self) -> Option<String> {
+        match (&self.default_remote, &self.repository_url) {
+            (Some(name), _) => Some(name.clone()),
+            (None, Some(_)) => Some(DEFAULT_REMOTE_NAME.to_string()),
+            (None, None) if self.remotes.len() == 1 => self.remotes.keys().next().cloned(),
+            (None, None) => None,
+        }
+    }
 }
 
-pub fn write_config(config: &Config, path: &PathBuf) -> Result<(), Error> {
-    let content = toml::to_string_pretty(config).unwrap();
+/// Writes config as TOML. Existing file is replaced, so its comments and
+/// unknown keys are not kept.
+pub fn write_config(config: &impl Serialize, path: &PathBuf) -> Result<(), ConfigError> {
+    let content =
+        toml::to_string_pretty(config).map_err(|e| ConfigError::Serialize(path.clone(), e))?;
+    let write_error = |e| ConfigError::Write(path.clone(), e);
     if let Some(parent) = path.parent() {
         info!("Create local repository: {:?}", parent);
-        fs::create_dir_all(parent)?;
+        fs::create_dir_all(parent).map_err(write_error)?;
     }
-    fs::write(path, content)?;
+    fs::write(path, content).map_err(write_error)?;
     Ok(())
 }
 
-pub fn write_default_config(path: &PathBuf) -> Result<Config, Error> {
+pub fn write_default_config(path: &PathBuf) -> Result<Config, ConfigError> {
     let local_repository_path = path.parent().unwrap().to_path_buf();
     let config = Config::new(local_repository_path);
     write_config(&config, path)?;
@@ -66,32 +129,536 @@ pub fn ask_for_confirmation(prompt: &str) -> bool {
     input == "y" || input == "yes"
 }
 
-pub fn init_or_read_config() -> Config {
-    let local_config_path = env::current_dir()
-        .unwrap()
-        .join(OCA_DIR_NAME)
-        .join("config.toml");
-    if local_config_path.is_file() {
-        read_config(&local_config_path).unwrap()
-    } else {
-        // Try to read home directory configuration
-        let p = dirs::home_dir()
-            .unwrap()
-            .join(OCA_DIR_NAME)
-            .join("config.toml");
-        match read_config(&p) {
-            Ok(config) => config,
-            Err(_) => {
-                if ask_for_confirmation("OCA config not found do you want to initialize it in your home directory? (y/N)") {
-                write_default_config(&p).unwrap()
-             } else {
-                println!("Consider running oca init in this directory to initialize local repository");
-                process::exit(1)
-             }
+/// Config file values. Every field is optional, so files can be layered on
+/// top of each other.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct PartialConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_repository_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_remote: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presentation_endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub remotes: BTreeMap<String, Remote>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lint: BTreeMap<String, Severity>,
+}
+
+impl PartialConfig {
+    /// Fills fields missing in `self` with values from `lower` layer. Remotes
+    /// and lint rules are merged by name.
+    fn merge(self, lower: PartialConfig) -> PartialConfig {
+        let mut remotes = lower.remotes;
+        remotes.extend(self.remotes);
+        let mut lint = lower.lint;
+        lint.extend(self.lint);
+        PartialConfig {
+            local_repository_path: self.local_repository_path.or(lower.local_repository_path),
+            repository_url: self.repository_url.or(lower.repository_url),
+            default_remote: self.default_remote.or(lower.default_remote),
+            presentation_endpoint: self.presentation_endpoint.or(lower.presentation_endpoint),
+            remotes,
+            lint,
+        }
+    }
+
+    /// Overrides fields with `OCA_*` variables returned by `var`.
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) {
+        for key in ConfigKey::SCALARS {
+            if let Some(value) = key.env_var().and_then(&var) {
+                self.set(key, value);
+            }
+        }
+    }
+
+    pub fn get(&self, key: &ConfigKey) -> Option<String> {
+        match key {
+            ConfigKey::LocalRepositoryPath => self
+                .local_repository_path
+                .as_ref()
+                .map(|path| path.display().to_string()),
+            ConfigKey::RepositoryUrl => self.repository_url.clone(),
+            ConfigKey::DefaultRemote => self.default_remote.clone(),
+            ConfigKey::PresentationEndpoint => self.presentation_endpoint.clone(),
+            ConfigKey::RemoteUrl(name) => self.remotes.get(name).map(|remote| remote.url.clone()),
+            ConfigKey::LintSeverity(rule) => self.lint.get(rule).map(ToString::to_string),
+        }
+    }
+
+    pub fn set(&mut self, key: &ConfigKey, value: String) {
+        match key {
+            ConfigKey::LocalRepositoryPath => self.local_repository_path = Some(value.into()),
+            ConfigKey::RepositoryUrl => self.repository_url = Some(value),
+            ConfigKey::DefaultRemote => self.default_remote = Some(value),
+            ConfigKey::PresentationEndpoint => self.presentation_endpoint = Some(value),
+            ConfigKey::RemoteUrl(name) => {
+                self.remotes.insert(name.clone(), Remote { url: value });
+            }
+            ConfigKey::LintSeverity(rule) => {
+                // Value is checked by `oca config set` before it's saved.
+                if let Ok(severity) = value.parse() {
+                    self.lint.insert(rule.clone(), severity);
+                }
+            }
+        }
+    }
+
+    /// Removes value of given key. Returns false if it wasn't set.
+    pub fn unset(&mut self, key: &ConfigKey) -> bool {
+        match key {
+            ConfigKey::LocalRepositoryPath => self.local_repository_path.take().is_some(),
+            ConfigKey::RepositoryUrl => self.repository_url.take().is_some(),
+            ConfigKey::DefaultRemote => self.default_remote.take().is_some(),
+            ConfigKey::PresentationEndpoint => self.presentation_endpoint.take().is_some(),
+            ConfigKey::RemoteUrl(name) => self.remotes.remove(name).is_some(),
+            ConfigKey::LintSeverity(rule) => self.lint.remove(rule).is_some(),
+        }
+    }
+
+    /// Returns all keys that have value set.
+    pub fn keys(&self) -> Vec<ConfigKey> {
+        ConfigKey::SCALARS
+            .iter()
+            .cloned()
+            .chain(self.remotes.keys().cloned().map(ConfigKey::RemoteUrl))
+            .chain(self.lint.keys().cloned().map(ConfigKey::LintSeverity))
+            .filter(|key| self.get(key).is_some())
+            .collect()
+    }
+}
+
+/// Name of single config value, as used by `oca config` commands.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigKey {
+    LocalRepositoryPath,
+    RepositoryUrl,
+    DefaultRemote,
+    PresentationEndpoint,
+    /// `remotes.<name>.url`
+    RemoteUrl(String),
+    /// `lint.<rule>`
+    LintSeverity(String),
+}
+
+impl ConfigKey {
+    pub const SCALARS: &'static [ConfigKey] = &[
+        ConfigKey::LocalRepositoryPath,
+        ConfigKey::RepositoryUrl,
+        ConfigKey::DefaultRemote,
+        ConfigKey::PresentationEndpoint,
+    ];
+
+    /// Name of environment variable that overrides the value.
+    pub fn env_var(&self) -> Option<&'static str> {
+        match self {
+            ConfigKey::LocalRepositoryPath => Some(OCA_LOCAL_REPOSITORY_PATH_ENV),
+            ConfigKey::RepositoryUrl => Some(OCA_REPOSITORY_URL_ENV),
+            ConfigKey::DefaultRemote => Some(OCA_DEFAULT_REMOTE_ENV),
+            ConfigKey::PresentationEndpoint => Some(OCA_PRESENTATION_ENDPOINT_ENV),
+            ConfigKey::RemoteUrl(_) | ConfigKey::LintSeverity(_) => None,
+        }
+    }
+}
+
+impl std::str::FromStr for ConfigKey {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local_repository_path" => Ok(ConfigKey::LocalRepositoryPath),
+            "repository_url" => Ok(ConfigKey::RepositoryUrl),
+            "default_remote" => Ok(ConfigKey::DefaultRemote),
+            "presentation_endpoint" => Ok(ConfigKey::PresentationEndpoint),
+            _ if s.starts_with("lint.") => match &s["lint.".len()..] {
+                rule if !rule.is_empty() && !rule.contains('.') => {
+                    Ok(ConfigKey::LintSeverity(rule.to_string()))
+                }
+                _ => Err(ConfigError::UnknownKey(s.to_string())),
+            },
+            _ => match s
+                .strip_prefix("remotes.")
+                .and_then(|rest| rest.strip_suffix(".url"))
+            {
+                Some(name) if !name.is_empty() && !name.contains('.') => {
+                    Ok(ConfigKey::RemoteUrl(name.to_string()))
+                }
+                _ => Err(ConfigError::UnknownKey(s.to_string())),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigKey::LocalRepositoryPath => write!(f, "local_repository_path"),
+            ConfigKey::RepositoryUrl => write!(f, "repository_url"),
+            ConfigKey::DefaultRemote => write!(f, "default_remote"),
+            ConfigKey::PresentationEndpoint => write!(f, "presentation_endpoint"),
+            ConfigKey::RemoteUrl(name) => write!(f, "remotes.{}.url", name),
+            ConfigKey::LintSeverity(rule) => write!(f, "lint.{}", rule),
+        }
+    }
+}
+
+/// Place config file was found in, ordered from the most to the least
+/// important.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Path given with `--config-path`.
+    Argument,
+    /// Path given in `OCA_CONFIG` environment variable.
+    Environment,
+    /// `.oca/config.toml` in current directory or one of its parents.
+    Project,
+    /// `~/.oca/config.toml`.
+    Home,
+    /// System wide config file.
+    System,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ConfigSource::Argument => "argument",
+            ConfigSource::Environment => "environment",
+            ConfigSource::Project => "project",
+            ConfigSource::Home => "home",
+            ConfigSource::System => "system",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigLayer {
+    pub source: ConfigSource,
+    pub path: PathBuf,
+    pub config: PartialConfig,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Unknown config key: {0}. Known keys: local_repository_path, repository_url, default_remote, presentation_endpoint, remotes.<name>.url, lint.<rule>")]
+    UnknownKey(String),
+    #[error("Invalid value of {0}: {1}")]
+    InvalidValue(String, String),
+    #[error("Config key {0} is not set")]
+    NotSet(String),
+    #[error("Config key {0} is not set in {1}")]
+    NotSetIn(String, PathBuf),
+    #[error("Can't find config file to edit. Use `--config-path` to point to one")]
+    NoEditableFile,
+    #[error("Config file {0} doesn't exist")]
+    MissingFile(PathBuf),
+    #[error("Can't read config file {0}: {1}")]
+    Read(PathBuf, io::Error),
+    #[error("Invalid config file {0}: {1}")]
+    Parse(PathBuf, toml::de::Error),
+    #[error("Can't write config file {0}: {1}")]
+    Write(PathBuf, io::Error),
+    #[error("Can't serialize config for {0}: {1}")]
+    Serialize(PathBuf, toml::ser::Error),
+    #[error("`local_repository_path` is not set in any config file. Set it in config or with OCA_LOCAL_REPOSITORY_PATH variable")]
+    MissingLocalRepositoryPath,
+    #[error("OCA config not initialized. Consider running `oca init` in this directory to initialize local repository")]
+    NotInitialized,
+    #[error("OCA config not found. Searched in: {}. Run `oca init` to initialize local repository, or point to config file with `--config-path` or OCA_CONFIG variable", .0.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", "))]
+    NotFound(Vec<PathBuf>),
+}
+
+/// Returns true if user can't be asked questions: `--non-interactive` flag is
+/// set, `CI` variable is set, or stdin is not a terminal.
+pub fn is_non_interactive(non_interactive: bool) -> bool {
+    let ci = env::var("CI")
+        .map(|ci| !ci.is_empty() && ci != "0" && ci != "false")
+        .unwrap_or(false);
+    non_interactive || ci || !io::stdin().is_terminal()
+}
+
+pub fn config_file(dir: &Path) -> PathBuf {
+    dir.join(OCA_DIR_NAME).join(CONFIG_FILE_NAME)
+}
+
+/// Looks up environment variable by name.
+pub type EnvLookup = Box<dyn Fn(&str) -> Option<String>>;
+
+/// Environment variables and home and system locations config files are
+/// looked up in.
+pub struct ConfigEnv {
+    pub var: EnvLookup,
+    pub home_dir: Option<PathBuf>,
+    pub system_path: PathBuf,
+}
+
+impl ConfigEnv {
+    /// Environment of running process.
+    pub fn current() -> Self {
+        Self {
+            var: Box::new(env_var),
+            home_dir: dirs::home_dir(),
+            system_path: PathBuf::from(SYSTEM_CONFIG_PATH),
+        }
+    }
+}
+
+/// Returns paths where config files are looked for, in order of importance.
+fn config_candidates(
+    config_path: Option<&Path>,
+    current_dir: &Path,
+    env: &ConfigEnv,
+) -> Vec<(ConfigSource, PathBuf)> {
+    let mut candidates = vec![];
+    if let Some(path) = config_path {
+        candidates.push((ConfigSource::Argument, path.to_path_buf()));
+    }
+    if let Some(path) = (env.var)(OCA_CONFIG_ENV) {
+        candidates.push((ConfigSource::Environment, PathBuf::from(path)));
+    }
+    if let Some(path) = current_dir
+        .ancestors()
+        .map(config_file)
+        .find(|path| path.is_file())
+    {
+        candidates.push((ConfigSource::Project, path));
+    }
+    if let Some(home) = &env.home_dir {
+        candidates.push((ConfigSource::Home, config_file(home)));
+    }
+    candidates.push((ConfigSource::System, env.system_path.clone()));
+    candidates
+}
+
+/// Reads config file as it is, without resolving relative paths.
+pub fn read_partial_config(path: &Path) -> Result<PartialConfig, ConfigError> {
+    let content = fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_owned(), e))?;
+    toml::from_str(&content).map_err(|e| ConfigError::Parse(path.to_owned(), e))
+}
+
+fn read_layer(source: ConfigSource, path: PathBuf) -> Result<ConfigLayer, ConfigError> {
+    let mut config = read_partial_config(&path)?;
+    // Relative repository path is relative to config file location.
+    if let (Some(repo_path), Some(dir)) = (&config.local_repository_path, path.parent()) {
+        if repo_path.is_relative() {
+            config.local_repository_path = Some(dir.join(repo_path));
+        }
+    }
+    Ok(ConfigLayer {
+        source,
+        path,
+        config,
+    })
+}
+
+/// Reads all existing config files. Files given explicitly, by argument or
+/// `OCA_CONFIG` variable, must exist.
+pub fn config_layers(
+    config_path: Option<&Path>,
+    current_dir: &Path,
+    env: &ConfigEnv,
+) -> Result<Vec<ConfigLayer>, ConfigError> {
+    let mut layers: Vec<ConfigLayer> = vec![];
+    for (source, path) in config_candidates(config_path, current_dir, env) {
+        let explicit = matches!(source, ConfigSource::Argument | ConfigSource::Environment);
+        if !path.is_file() {
+            if explicit {
+                return Err(ConfigError::MissingFile(path));
             }
+            continue;
         }
+        if layers.iter().any(|layer| layer.path == path) {
+            continue;
+        }
+        layers.push(read_layer(source, path)?);
+    }
+    Ok(layers)
+}
+
+/// Returns config file that should be edited: home config if `global` is set,
+/// otherwise the most important of explicit, `OCA_CONFIG` or project config.
+/// Falls back to home config.
+pub fn editable_config_file(
+    config_path: Option<&Path>,
+    current_dir: &Path,
+    global: bool,
+    env: &ConfigEnv,
+) -> Option<PathBuf> {
+    let mut candidates = config_candidates(config_path, current_dir, env).into_iter();
+    if global {
+        return candidates
+            .find(|(source, _)| *source == ConfigSource::Home)
+            .map(|(_, path)| path);
+    }
+    candidates
+        .find(|(source, _)| *source != ConfigSource::System)
+        .map(|(_, path)| path)
+}
+
+/// Merges layers and `OCA_*` environment overrides into final config.
+pub fn merge_layers(
+    layers: &[ConfigLayer],
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Config, ConfigError> {
+    let mut merged = layers
+        .iter()
+        .rev()
+        .fold(PartialConfig::default(), |lower, layer| {
+            layer.config.clone().merge(lower)
+        });
+    merged.apply_env(var);
+    Ok(Config {
+        local_repository_path: merged
+            .local_repository_path
+            .ok_or(ConfigError::MissingLocalRepositoryPath)?,
+        repository_url: merged.repository_url,
+        default_remote: merged.default_remote,
+        presentation_endpoint: merged.presentation_endpoint,
+        remotes: merged.remotes,
+        lint: merged.lint,
+    })
+}
+
+pub fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// Reads layered configuration. If no config file is found, user is asked to
+/// initialize one in home directory, unless running in non-interactive mode.
+pub fn init_or_read_config(
+    config_path: Option<&Path>,
+    non_interactive: bool,
+) -> Result<Config, ConfigError> {
+    let current_dir = env::current_dir().map_err(|e| ConfigError::Read(PathBuf::from("."), e))?;
+    let env = ConfigEnv::current();
+    let layers = config_layers(config_path, &current_dir, &env)?;
+    if !layers.is_empty() || (env.var)(OCA_LOCAL_REPOSITORY_PATH_ENV).is_some() {
+        return merge_layers(&layers, &env.var);
+    }
+
+    let searched = config_candidates(config_path, &current_dir, &env)
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect::<Vec<_>>();
+    let home_config = match &env.home_dir {
+        Some(home) if !is_non_interactive(non_interactive) => config_file(home),
+        _ => return Err(ConfigError::NotFound(searched)),
+    };
+    if ask_for_confirmation(
+        "OCA config not found do you want to initialize it in your home directory? (y/N)",
+    ) {
+        write_default_config(&home_config)
+    } else {
+        Err(ConfigError::NotInitialized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use super::*;
+
+    #[test]
+    fn test_layered_config() -> anyhow::Result<()> {
+        let tmp_dir = tempdir::TempDir::new("config")?;
+        let project = tmp_dir.path().join("project");
+        let nested = project.join("schemas").join("nested");
+        fs::create_dir_all(&nested)?;
+        let system_like = tmp_dir.path().join("explicit.toml");
+
+        write_config(
+            &Config {
+                repository_url: Some("https://project.example.com/".to_string()),
+                ..Config::new(PathBuf::from("repo"))
+            },
+            &config_file(&project),
+        )?;
+        fs::write(
+            &system_like,
+            "repository_url = \"https://explicit.example.com/\"\n\n[remotes.staging]\nurl = \"https://staging.example.com/\"\n",
+        )?;
+
+        let env = ConfigEnv {
+            var: Box::new(|_| None),
+            home_dir: Some(tmp_dir.path().join("home")),
+            system_path: tmp_dir.path().join("system.toml"),
+        };
+
+        // Project config is found from nested directory.
+        let layers = config_layers(Some(&system_like), &nested, &env)?;
+        assert_eq!(layers[0].source, ConfigSource::Argument);
+        assert_eq!(layers[1].source, ConfigSource::Project);
+        assert_eq!(layers[1].path, config_file(&project));
+
+        let config = merge_layers(&layers, |_| None)?;
+        assert_eq!(
+            config.repository_url.as_deref(),
+            Some("https://explicit.example.com/")
+        );
+        assert_eq!(
+            config.local_repository_path,
+            project.join(OCA_DIR_NAME).join("repo")
+        );
+        assert!(config.remotes.contains_key("staging"));
+
+        let vars: HashMap<&str, &str> = [
+            (OCA_REPOSITORY_URL_ENV, "https://env.example.com/"),
+            (OCA_DEFAULT_REMOTE_ENV, "staging"),
+        ]
+        .into_iter()
+        .collect();
+        let config = merge_layers(&layers, |name| vars.get(name).map(|v| v.to_string()))?;
+        assert_eq!(
+            config.repository_url.as_deref(),
+            Some("https://env.example.com/")
+        );
+        assert_eq!(config.default_remote.as_deref(), Some("staging"));
+
+        let missing = tmp_dir.path().join("missing.toml");
+        assert!(matches!(
+            config_layers(Some(&missing), &nested, &env),
+            Err(ConfigError::MissingFile(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_keys() -> anyhow::Result<()> {
+        let key: ConfigKey = "remotes.staging.url".parse()?;
+        assert_eq!(key, ConfigKey::RemoteUrl("staging".to_string()));
+        assert_eq!(key.to_string(), "remotes.staging.url");
+        assert!("remotes.url".parse::<ConfigKey>().is_err());
+        assert!("unknown".parse::<ConfigKey>().is_err());
+
+        let mut config = PartialConfig::default();
+        config.set(&key, "https://staging.example.com/".to_string());
+        config.set(
+            &ConfigKey::RepositoryUrl,
+            "https://example.com/".to_string(),
+        );
+        assert_eq!(config.keys(), vec![ConfigKey::RepositoryUrl, key.clone()]);
+        assert_eq!(
+            config.get(&key).as_deref(),
+            Some("https://staging.example.com/")
+        );
+
+        assert!(config.unset(&key));
+        assert!(!config.unset(&key));
+        assert_eq!(config.keys(), vec![ConfigKey::RepositoryUrl]);
+
+        let key: ConfigKey = "lint.information-overlay".parse()?;
+        assert_eq!(
+            key,
+            ConfigKey::LintSeverity("information-overlay".to_string())
+        );
+        config.set(&key, "off".to_string());
+        assert_eq!(config.lint["information-overlay"], Severity::Off);
+        assert_eq!(config.get(&key).as_deref(), Some("off"));
+
+        Ok(())
     }
-    // Check currnet path
-    // Check home
-    // ask to initialize home or run oca init to create it in local directory
 }
@@ -0,0 +1,174 @@
+use std::{collections::BTreeSet, env, path::Path, str::FromStr};
+
+use clap::Subcommand;
+
+use crate::{
+    config::{
+        config_layers, editable_config_file, env_var, read_partial_config, write_config, ConfigEnv,
+        ConfigError, ConfigKey, ConfigLayer, PartialConfig, DEFAULT_REMOTE_NAME,
+    },
+    error::CliError,
+    lint::{self, Severity},
+    utils::parse_url,
+};
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Show effective config and where each value comes from
+    List,
+    /// Print effective value of config key
+    Get {
+        /// Config key, e.g. `repository_url` or `remotes.<name>.url`
+        key: String,
+    },
+    /// Set value of config key in config file. The file is rewritten, so
+    /// its comments and unknown keys are dropped
+    Set {
+        /// Config key, e.g. `repository_url` or `remotes.<name>.url`
+        key: String,
+        value: String,
+        /// Edit config in home directory instead of project one
+        #[arg(long)]
+        global: bool,
+    },
+    /// Remove config key from config file. The file is rewritten, so its
+    /// comments and unknown keys are dropped
+    Unset {
+        /// Config key, e.g. `repository_url` or `remotes.<name>.url`
+        key: String,
+        /// Edit config in home directory instead of project one
+        #[arg(long)]
+        global: bool,
+    },
+}
+
+/// Returns effective value of key and description of where it comes from.
+fn lookup(layers: &[ConfigLayer], key: &ConfigKey) -> Option<(String, String)> {
+    if let Some(var) = key.env_var() {
+        if let Some(value) = env_var(var) {
+            return Some((value, format!("env {}", var)));
+        }
+    }
+    layers.iter().find_map(|layer| {
+        layer
+            .config
+            .get(key)
+            .map(|value| (value, format!("{} {}", layer.source, layer.path.display())))
+    })
+}
+
+/// Checks value and returns it in the form it should be saved in.
+fn validate(
+    key: &ConfigKey,
+    value: String,
+    layers: &[ConfigLayer],
+    current_dir: &Path,
+) -> Result<String, CliError> {
+    let invalid = |reason: String| ConfigError::InvalidValue(key.to_string(), reason);
+    match key {
+        ConfigKey::RepositoryUrl | ConfigKey::RemoteUrl(_) => {
+            let url = parse_url(value).map_err(|e| invalid(e.to_string()))?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(invalid(format!("unsupported url scheme: {}", url.scheme())).into());
+            }
+            Ok(url.to_string())
+        }
+        ConfigKey::LocalRepositoryPath => {
+            let path = current_dir.join(value);
+            if path.exists() && !path.is_dir() {
+                return Err(invalid(format!("{} is not a directory", path.display())).into());
+            }
+            Ok(path.display().to_string())
+        }
+        ConfigKey::DefaultRemote => {
+            let known = value == DEFAULT_REMOTE_NAME
+                || layers
+                    .iter()
+                    .any(|layer| layer.config.remotes.contains_key(&value));
+            if known {
+                Ok(value)
+            } else {
+                Err(invalid(format!("unknown remote: {}", value)).into())
+            }
+        }
+        ConfigKey::PresentationEndpoint => {
+            let endpoint = value.trim_matches('/').to_string();
+            if endpoint.is_empty() {
+                return Err(invalid("endpoint can't be empty".to_string()).into());
+            }
+            Ok(endpoint)
+        }
+        ConfigKey::LintSeverity(rule) => {
+            if lint::rule(rule).is_none() {
+                return Err(invalid(format!("unknown lint rule: {}", rule)).into());
+            }
+            let severity = value.parse::<Severity>().map_err(invalid)?;
+            Ok(severity.to_string())
+        }
+    }
+}
+
+pub fn handle_config_command(
+    command: &ConfigCommand,
+    config_path: Option<&Path>,
+) -> Result<(), CliError> {
+    let current_dir = env::current_dir().map_err(CliError::CurrentDirFailed)?;
+    let env = ConfigEnv::current();
+    let layers = config_layers(config_path, &current_dir, &env)?;
+    match command {
+        ConfigCommand::List => {
+            let keys = ConfigKey::SCALARS
+                .iter()
+                .filter(|key| key.env_var().and_then(env_var).is_some())
+                .cloned()
+                .chain(layers.iter().flat_map(|layer| layer.config.keys()))
+                .collect::<BTreeSet<_>>();
+            for key in keys {
+                if let Some((value, origin)) = lookup(&layers, &key) {
+                    println!("{} = {:?} ({})", key, value, origin);
+                }
+            }
+            Ok(())
+        }
+        ConfigCommand::Get { key } => {
+            let key = ConfigKey::from_str(key)?;
+            match lookup(&layers, &key) {
+                Some((value, _)) => {
+                    println!("{}", value);
+                    Ok(())
+                }
+                None => Err(ConfigError::NotSet(key.to_string()).into()),
+            }
+        }
+        ConfigCommand::Set { key, value, global } => {
+            let key = ConfigKey::from_str(key)?;
+            let value = validate(&key, value.clone(), &layers, &current_dir)?;
+            let path = editable_config_file(config_path, &current_dir, *global, &env)
+                .ok_or(ConfigError::NoEditableFile)?;
+            let mut config = if path.is_file() {
+                read_partial_config(&path)?
+            } else {
+                PartialConfig::default()
+            };
+            config.set(&key, value.clone());
+            write_config(&config, &path)?;
+            println!("Set {} = {:?} in {}", key, value, path.display());
+            Ok(())
+        }
+        ConfigCommand::Unset { key, global } => {
+            let key = ConfigKey::from_str(key)?;
+            let path = editable_config_file(config_path, &current_dir, *global, &env)
+                .ok_or(ConfigError::NoEditableFile)?;
+            if !path.is_file() {
+                return Err(ConfigError::MissingFile(path).into());
+            }
+            let mut config = read_partial_config(&path)?;
+            if !config.unset(&key) {
+                return Err(ConfigError::NotSetIn(key.to_string(), path).into());
+            }
+            write_config(&config, &path)?;
+            println!("Removed {} from {}", key, path.display());
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use isolang::Language;
+use oca_bundle_semantics::state::oca::OCABundle;
+use oca_rs::Facade;
+use serde::Serialize;
+
+use crate::{
+    dependency_graph::{parse_name, MutableGraph},
+    diff::{overlay_attributes, overlay_fields},
+    error::CliError,
+};
+
+/// Overlays that are expected in every language, with overlay that selects
+/// attributes they apply to. `None` means all attributes.
+const OVERLAYS: &[(&str, Option<&str>)] = &[
+    ("label", None),
+    ("information", None),
+    ("entry", Some("entry_code")),
+];
+
+/// Coverage of single overlay type in single language of OCA bundle.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct CoverageRow {
+    pub bundle: String,
+    pub language: String,
+    pub overlay: &'static str,
+    /// Number of attributes the overlay should cover.
+    pub expected: usize,
+    pub missing: Vec<String>,
+}
+
+impl CoverageRow {
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Returns ISO 639-3 code of language given as 639-3 or 639-1 code.
+pub fn normalize_language(code: &str) -> Option<String> {
+    let code = code.to_lowercase();
+    Language::from_639_3(&code)
+        .or_else(|| Language::from_639_1(&code))
+        .map(|language| language.to_639_3().to_string())
+}
+
+/// Returns coverage rows of bundle for every language used by its label,
+/// information or entry overlays, and every required language.
+pub fn bundle_coverage(name: &str, bundle: &OCABundle, required: &[String]) -> Vec<CoverageRow> {
+    let overlays = overlay_fields(bundle)
+        .into_iter()
+        .map(|((overlay_type, language), fields)| {
+            let language = language.map(|l| normalize_language(&l).unwrap_or(l));
+            (overlay_type, language, fields)
+        })
+        .collect::<Vec<_>>();
+    let attributes_of = |overlay_type: &str, language: Option<&str>| {
+        overlays
+            .iter()
+            .filter(|(t, l, _)| t == overlay_type && l.as_deref() == language)
+            .flat_map(|(_, _, fields)| overlay_attributes(fields).map(str::to_string))
+            .collect::<BTreeSet<_>>()
+    };
+
+    let mut languages = overlays
+        .iter()
+        .filter(|(t, _, _)| OVERLAYS.iter().any(|(overlay, _)| overlay == t))
+        .filter_map(|(_, l, _)| l.clone())
+        .collect::<BTreeSet<_>>();
+    languages.extend(required.iter().cloned());
+
+    let attributes = bundle
+        .capture_base
+        .attributes
+        .keys()
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    let mut rows = vec![];
+    for language in &languages {
+        for (overlay, selector) in OVERLAYS {
+            let expected = match selector {
+                Some(selector) => attributes_of(selector, None),
+                None => attributes.clone(),
+            };
+            if expected.is_empty() {
+                continue;
+            }
+            let present = attributes_of(overlay, Some(language));
+            rows.push(CoverageRow {
+                bundle: name.to_string(),
+                language: language.clone(),
+                overlay,
+                expected: expected.len(),
+                missing: expected.difference(&present).cloned().collect(),
+            });
+        }
+    }
+    rows
+}
+
+/// Validates ocafile against ocafiles in graph and returns coverage of OCA
+/// bundle built from it. Bundle is named by its refn, or by path if it has
+/// none.
+pub fn file_coverage(
+    facade: &Facade,
+    graph: &mut MutableGraph,
+    path: &Path,
+    required: &[String],
+) -> Result<Vec<CoverageRow>, CliError> {
+    let source =
+        fs::read_to_string(path).map_err(|e| CliError::ReadFileFailed(path.to_owned(), e))?;
+    let (refn, _) = parse_name(path).map_err(|e| CliError::GraphError(e.into()))?;
+    let bundle = facade
+        .validate_ocafile_with_external_references(source, graph)
+        .map_err(|e| CliError::GrammarError(path.to_owned(), e))?
+        .oca_bundle;
+    let name = refn.unwrap_or_else(|| path.display().to_string());
+    Ok(bundle_coverage(&name, &bundle, required))
+}
+
+/// Returns coverage of all ocafiles, and number of bundles that don't fully
+/// cover required languages.
+pub fn coverage(
+    facade: &Facade,
+    paths: &[PathBuf],
+    required: &[String],
+) -> Result<(Vec<CoverageRow>, usize), CliError> {
+    let mut graph = MutableGraph::new(paths.to_vec())?;
+    let mut rows = vec![];
+    for path in paths {
+        rows.extend(file_coverage(facade, &mut graph, path, required)?);
+    }
+    let incomplete = rows
+        .iter()
+        .filter(|row| !row.is_complete() && required.contains(&row.language))
+        .map(|row| &row.bundle)
+        .collect::<BTreeSet<_>>()
+        .len();
+    Ok((rows, incomplete))
+}
+
+/// Formats coverage as table with one row per bundle, language and overlay.
+pub fn format_coverage(rows: &[CoverageRow]) -> String {
+    let width = |header: &str, column: &dyn Fn(&CoverageRow) -> usize| {
+        rows.iter().map(column).max().unwrap_or(0).max(header.len())
+    };
+    let bundle_width = width("BUNDLE", &|row| row.bundle.len());
+    let language_width = width("LANGUAGE", &|row| row.language.len());
+    let overlay_width = width("OVERLAY", &|row| row.overlay.len());
+    let mut lines = vec![format!(
+        "{:<bundle_width$}  {:<language_width$}  {:<overlay_width$}  {:<8}  MISSING",
+        "BUNDLE", "LANGUAGE", "OVERLAY", "COVERAGE",
+    )];
+    lines.extend(rows.iter().map(|row| {
+        let covered = format!("{}/{}", row.expected - row.missing.len(), row.expected);
+        format!(
+            "{:<bundle_width$}  {:<language_width$}  {:<overlay_width$}  {:<8}  {}",
+            row.bundle,
+            row.language,
+            row.overlay,
+            covered,
+            row.missing.join(", ")
+        )
+        .trim_end()
+        .to_string()
+    }));
+    lines.join("\n")
+}
+
+#[test]
+fn test_coverage() -> anyhow::Result<()> {
+    let tmp_dir = tempdir::TempDir::new("coverage")?;
+    let mut facade = crate::get_oca_facade(tmp_dir.path().to_path_buf());
+    let ocafile = r#"ADD ATTRIBUTE name=Text age=Numeric radio=Text
+ADD LABEL en ATTRS name="Name" age="Age" radio="Radio"
+ADD LABEL pl ATTRS name="Imię"
+ADD INFORMATION en ATTRS name="Name of person"
+ADD ENTRY_CODE ATTRS radio=["o1", "o2"]
+ADD ENTRY en ATTRS radio={"o1": "One", "o2": "Two"}
+"#;
+    let bundle = facade
+        .validate_ocafile(ocafile.to_string())
+        .unwrap()
+        .oca_bundle;
+
+    let rows = bundle_coverage("person", &bundle, &[]);
+    let missing = |language: &str, overlay: &str| {
+        rows.iter()
+            .find(|row| row.language == language && row.overlay == overlay)
+            .map(|row| row.missing.clone())
+    };
+    assert_eq!(missing("eng", "label"), Some(vec![]));
+    assert_eq!(
+        missing("eng", "information"),
+        Some(vec!["age".to_string(), "radio".to_string()])
+    );
+    assert_eq!(
+        missing("pol", "label"),
+        Some(vec!["age".to_string(), "radio".to_string()])
+    );
+    assert_eq!(missing("pol", "entry"), Some(vec!["radio".to_string()]));
+
+    let rows = bundle_coverage("person", &bundle, &["deu".to_string()]);
+    assert!(rows
+        .iter()
+        .filter(|row| row.language == "deu")
+        .all(|row| !row.is_complete()));
+    assert_eq!(normalize_language("en").as_deref(), Some("eng"));
+    assert_eq!(normalize_language("xx"), None);
+
+    Ok(())
+}
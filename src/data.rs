@@ -0,0 +1,551 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use clap::Subcommand;
+use oca_ast_semantics::ast::{NestedAttrType, RefValue};
+use oca_bundle_semantics::state::oca::OCABundle;
+use oca_rs::Facade;
+use regex::Regex;
+use said::SelfAddressingIdentifier;
+use serde_json::{Map, Value};
+
+use crate::{
+    diff::{overlay_fields, overlay_values},
+    error::CliError,
+    get_oca_facade,
+    list::attribute_type_name,
+    open_history,
+    sample::sample_records,
+    utils::BundleSelector,
+};
+
+#[derive(Subcommand)]
+pub enum DataCommand {
+    /// Check data records against OCA bundle
+    Validate {
+        #[command(flatten)]
+        bundle: BundleSelector,
+        /// File with records: `.json` (array of objects), `.jsonl` or `.csv`.
+        /// Columns of CSV file name nested attributes with dots, e.g.
+        /// `person.name`
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+    /// Generate records that are valid against OCA bundle, as JSON array
+    Sample {
+        #[command(flatten)]
+        bundle: BundleSelector,
+        /// Number of records
+        #[arg(short, default_value_t = 10)]
+        n: usize,
+        /// Seed of random generator. The same seed gives the same records
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+}
+
+/// Type of attribute, with referenced bundles resolved.
+#[derive(Debug, Clone)]
+pub enum FieldType {
+    /// Name of attribute type, e.g. `Text`.
+    Value(String),
+    Array(Box<FieldType>),
+    Reference(Box<Schema>),
+    Null,
+}
+
+/// Attribute of OCA bundle with rules of its overlays.
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub field_type: FieldType,
+    pub entry_codes: Option<Vec<String>>,
+    pub format: Option<String>,
+    pub mandatory: bool,
+    pub cardinality: Option<String>,
+}
+
+/// Attributes of OCA bundle, including attributes of referenced bundles.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    pub fields: BTreeMap<String, Field>,
+}
+
+impl Schema {
+    /// Loads OCA bundle of given SAID from local repository.
+    pub fn load(facade: &Facade, said: SelfAddressingIdentifier) -> Result<Self, CliError> {
+        let bundle = facade
+            .get_oca_bundle(said, false)
+            .map_err(CliError::OcaBundleAstError)?
+            .bundle;
+        Self::from_bundle(facade, &bundle)
+    }
+
+    pub fn from_bundle(facade: &Facade, bundle: &OCABundle) -> Result<Self, CliError> {
+        let overlays = overlay_fields(bundle);
+        let entry_codes = overlay_values(&overlays, "entry_code", None);
+        let formats = overlay_values(&overlays, "format", None);
+        let conformance = overlay_values(&overlays, "conformance", None);
+        let cardinality = overlay_values(&overlays, "cardinality", None);
+
+        let mut fields = BTreeMap::new();
+        for (name, attr) in &bundle.capture_base.attributes {
+            let field = Field {
+                field_type: field_type(facade, attr)?,
+                // Entry codes given as SAID of another object can't be checked.
+                entry_codes: entry_codes.get(name).and_then(|codes| {
+                    codes.as_array().map(|codes| {
+                        codes
+                            .iter()
+                            .filter_map(|code| code.as_str().map(str::to_string))
+                            .collect()
+                    })
+                }),
+                format: formats
+                    .get(name)
+                    .and_then(|format| format.as_str().map(str::to_string)),
+                mandatory: conformance.get(name).and_then(Value::as_str) == Some("M"),
+                cardinality: cardinality
+                    .get(name)
+                    .and_then(|cardinality| cardinality.as_str().map(str::to_string)),
+            };
+            fields.insert(name.clone(), field);
+        }
+        Ok(Self { fields })
+    }
+}
+
+fn field_type(facade: &Facade, attr: &NestedAttrType) -> Result<FieldType, CliError> {
+    Ok(match attr {
+        NestedAttrType::Value(_) => FieldType::Value(attribute_type_name(attr)),
+        NestedAttrType::Array(inner) => FieldType::Array(Box::new(field_type(facade, inner)?)),
+        NestedAttrType::Reference(RefValue::Said(said)) => {
+            FieldType::Reference(Box::new(Schema::load(facade, said.clone())?))
+        }
+        NestedAttrType::Reference(RefValue::Name(refn)) => {
+            let said = facade
+                .fetch_all_refs()
+                .unwrap_or_default()
+                .remove(refn)
+                .ok_or_else(|| CliError::OCABundleRefnNotFound(refn.clone()))?;
+            FieldType::Reference(Box::new(Schema::load(facade, said.parse()?)?))
+        }
+        NestedAttrType::Null => FieldType::Null,
+    })
+}
+
+/// Problem with single field of record.
+#[derive(Debug, PartialEq)]
+pub struct FieldError {
+    /// Number of record, starting from 1.
+    pub record: usize,
+    /// Path of field, e.g. `person.addresses[0].street`.
+    pub field: String,
+    pub message: String,
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "record {}: {}: {}",
+            self.record, self.field, self.message
+        )
+    }
+}
+
+/// Converts OCA date format, e.g. `YYYY-MM-DD`, into regular expression.
+fn date_format_regex(format: &str) -> String {
+    let mut pattern = regex::escape(format);
+    for (token, regex) in [
+        ("YYYY", r"\d{4}"),
+        ("MM", r"\d{2}"),
+        ("DD", r"\d{2}"),
+        ("hh", r"\d{2}"),
+        ("mm", r"\d{2}"),
+        ("ss", r"\d{2}"),
+    ] {
+        pattern = pattern.replace(token, regex);
+    }
+    pattern
+}
+
+fn is_date_time(value: &str) -> bool {
+    DateTime::parse_from_rfc3339(value).is_ok()
+        || NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").is_ok()
+        || NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok()
+}
+
+/// Checks if cardinality such as `2`, `1-3`, `1-` or `-3` allows given number
+/// of items.
+fn cardinality_allows(cardinality: &str, count: usize) -> bool {
+    let bound = |value: &str| value.trim().parse::<usize>().ok();
+    match cardinality.split_once('-') {
+        Some((min, max)) => {
+            bound(min).is_none_or(|min| count >= min) && bound(max).is_none_or(|max| count <= max)
+        }
+        None => bound(cardinality).is_none_or(|exact| count == exact),
+    }
+}
+
+struct Validator {
+    record: usize,
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    fn error(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            record: self.record,
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn object(&mut self, path: &str, schema: &Schema, value: &Value) {
+        let object = match value {
+            Value::Object(object) => object,
+            other => {
+                let field = if path.is_empty() { "record" } else { path };
+                return self.error(field, format!("expected object, got {}", other));
+            }
+        };
+        let child = |name: &str| match path {
+            "" => name.to_string(),
+            _ => format!("{}.{}", path, name),
+        };
+        for (name, field) in &schema.fields {
+            match object.get(name) {
+                None | Some(Value::Null) if field.mandatory => {
+                    self.error(&child(name), "mandatory attribute is missing")
+                }
+                None | Some(Value::Null) => (),
+                Some(value) => self.field(&child(name), field, &field.field_type, value),
+            }
+        }
+        for name in object.keys() {
+            if !schema.fields.contains_key(name) {
+                self.error(&child(name), "unknown attribute");
+            }
+        }
+    }
+
+    fn field(&mut self, path: &str, field: &Field, field_type: &FieldType, value: &Value) {
+        match field_type {
+            FieldType::Array(inner) => {
+                let items = match value.as_array() {
+                    Some(items) => items,
+                    None => return self.error(path, format!("expected array, got {}", value)),
+                };
+                if let Some(cardinality) = &field.cardinality {
+                    if !cardinality_allows(cardinality, items.len()) {
+                        self.error(
+                            path,
+                            format!(
+                                "has {} items, but cardinality is {}",
+                                items.len(),
+                                cardinality
+                            ),
+                        );
+                    }
+                }
+                for (i, item) in items.iter().enumerate() {
+                    self.field(&format!("{}[{}]", path, i), field, inner, item);
+                }
+            }
+            FieldType::Reference(schema) => self.object(path, schema, value),
+            FieldType::Null => (),
+            FieldType::Value(attr_type) => self.value(path, field, attr_type, value),
+        }
+    }
+
+    fn value(&mut self, path: &str, field: &Field, attr_type: &str, value: &Value) {
+        let valid_type = match attr_type {
+            "Text" | "Binary" => value.is_string(),
+            "Numeric" => value.is_number(),
+            "Boolean" => value.is_boolean(),
+            "DateTime" => value.as_str().is_some_and(|value| {
+                // Values with format are checked against it below.
+                field.format.is_some() || is_date_time(value)
+            }),
+            _ => true,
+        };
+        if !valid_type {
+            return self.error(path, format!("expected {}, got {}", attr_type, value));
+        }
+        let text = match value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        if let Some(codes) = &field.entry_codes {
+            if !codes.contains(&text) {
+                self.error(
+                    path,
+                    format!("{} is not one of entry codes: {}", value, codes.join(", ")),
+                );
+            }
+        }
+        if let Some(format) = &field.format {
+            let pattern = match attr_type {
+                "DateTime" => date_format_regex(format),
+                // Format of binary data is its media type.
+                "Binary" => return,
+                _ => format.clone(),
+            };
+            match Regex::new(&format!("^(?:{})$", pattern)) {
+                Ok(regex) if !regex.is_match(&text) => {
+                    self.error(path, format!("{} doesn't match format {}", value, format))
+                }
+                Ok(_) => (),
+                Err(_) => warn!("Invalid format of {}: {}", path, format),
+            }
+        }
+    }
+}
+
+/// Checks records against schema and returns errors of all of them.
+pub fn validate_records(schema: &Schema, records: &[Value]) -> Vec<FieldError> {
+    let mut validator = Validator {
+        record: 0,
+        errors: vec![],
+    };
+    for (i, record) in records.iter().enumerate() {
+        validator.record = i + 1;
+        validator.object("", schema, record);
+    }
+    validator.errors
+}
+
+/// Splits CSV content into rows of fields. Fields can be quoted, with `""`
+/// standing for quote inside.
+fn parse_csv(content: &str) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') => (),
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|row| !(row.len() == 1 && row[0].is_empty()));
+    rows
+}
+
+/// Returns text, such as CSV cell or entry code, as value of attribute type.
+/// Text that isn't valid for the type is kept as string.
+pub(crate) fn typed_value(text: &str, attr_type: &str) -> Value {
+    match attr_type {
+        "Numeric" => serde_json::from_str(text)
+            .ok()
+            .filter(Value::is_number)
+            .or_else(|| {
+                text.parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number)
+            }),
+        "Boolean" => text.parse::<bool>().ok().map(Value::Bool),
+        _ => None,
+    }
+    .unwrap_or_else(|| Value::String(text.to_string()))
+}
+
+/// Converts CSV cell into JSON value of given type. Arrays are written as
+/// JSON, e.g. `["a", "b"]`.
+fn csv_value(cell: &str, field_type: &FieldType) -> Value {
+    match field_type {
+        FieldType::Value(attr_type) => typed_value(cell, attr_type),
+        _ => serde_json::from_str(cell).unwrap_or_else(|_| Value::String(cell.to_string())),
+    }
+}
+
+/// Turns CSV row into record, nesting columns such as `person.name`. Empty
+/// cells are treated as missing values.
+fn csv_record(header: &[String], row: &[String], schema: &Schema) -> Value {
+    let mut record = Map::new();
+    for (column, cell) in header.iter().zip(row) {
+        if cell.is_empty() {
+            continue;
+        }
+        let mut path = column.split('.').peekable();
+        let mut object = &mut record;
+        let mut schema = Some(schema);
+        while let Some(name) = path.next() {
+            let field = schema.and_then(|schema| schema.fields.get(name));
+            if path.peek().is_none() {
+                let value = match field {
+                    Some(field) => csv_value(cell, &field.field_type),
+                    None => Value::String(cell.clone()),
+                };
+                object.insert(name.to_string(), value);
+                break;
+            }
+            schema = match field.map(|field| &field.field_type) {
+                Some(FieldType::Reference(schema)) => Some(schema.as_ref()),
+                _ => None,
+            };
+            object = match object
+                .entry(name.to_string())
+                .or_insert_with(|| Value::Object(Map::new()))
+            {
+                Value::Object(object) => object,
+                // Column conflicts with another one, e.g. `a` and `a.b`.
+                _ => break,
+            };
+        }
+    }
+    Value::Object(record)
+}
+
+/// Reads records from `.json`, `.jsonl` or `.csv` file.
+pub fn read_records(path: &Path, schema: &Schema) -> Result<Vec<Value>, CliError> {
+    let content =
+        fs::read_to_string(path).map_err(|e| CliError::ReadFileFailed(path.to_owned(), e))?;
+    let invalid = |line: usize, e: serde_json::Error| {
+        CliError::RecordsReadError(path.to_owned(), line, e.to_string())
+    };
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => match serde_json::from_str(&content).map_err(|e| invalid(e.line(), e))? {
+            Value::Array(records) => Ok(records),
+            record => Ok(vec![record]),
+        },
+        Some("jsonl") => content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| serde_json::from_str(line).map_err(|e| invalid(i + 1, e)))
+            .collect(),
+        Some("csv") => {
+            let mut rows = parse_csv(&content).into_iter();
+            let header = rows.next().unwrap_or_default();
+            Ok(rows.map(|row| csv_record(&header, &row, schema)).collect())
+        }
+        other => Err(CliError::FileExtensionError(
+            other.unwrap_or_default().to_string(),
+        )),
+    }
+}
+
+pub fn handle_data_command(
+    command: &DataCommand,
+    local_repository_path: &Path,
+) -> Result<(), CliError> {
+    match command {
+        DataCommand::Validate { bundle, input } => {
+            let history = open_history(local_repository_path);
+            let facade = get_oca_facade(local_repository_path.to_path_buf());
+            let said = bundle.resolve(&facade, history.as_ref())?;
+            let schema = Schema::load(&facade, said)?;
+            let records = read_records(input, &schema)?;
+            let errors = validate_records(&schema, &records);
+            for error in &errors {
+                println!("{}", error);
+            }
+            let mut invalid = errors.iter().map(|e| e.record).collect::<Vec<_>>();
+            invalid.dedup();
+            println!(
+                "Checked {} records: {} valid, {} invalid, {} errors",
+                records.len(),
+                records.len() - invalid.len(),
+                invalid.len(),
+                errors.len()
+            );
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(CliError::DataValidationFailed(invalid.len()))
+            }
+        }
+        DataCommand::Sample { bundle, n, seed } => {
+            let history = open_history(local_repository_path);
+            let facade = get_oca_facade(local_repository_path.to_path_buf());
+            let said = bundle.resolve(&facade, history.as_ref())?;
+            let schema = Schema::load(&facade, said)?;
+            let seed = seed.unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_nanos() as u64)
+            });
+            let records = sample_records(&schema, *n, seed);
+            println!("{}", serde_json::to_string_pretty(&records).unwrap());
+            Ok(())
+        }
+    }
+}
+
+#[test]
+fn test_validate_records() -> anyhow::Result<()> {
+    let tmp_dir = tempdir::TempDir::new("data")?;
+    let mut facade = get_oca_facade(tmp_dir.path().to_path_buf());
+    facade
+        .build_from_ocafile("-- name=address\nADD ATTRIBUTE street=Text".to_string())
+        .unwrap();
+    let ocafile = r#"-- name=person
+ADD ATTRIBUTE name=Text age=Numeric address=refn:address tags=Array[Text] level=Text
+ADD CONFORMANCE ATTRS name="M"
+ADD ENTRY_CODE ATTRS level=["low", "high"]
+ADD CARDINALITY ATTRS tags="1-2"
+"#;
+    let bundle = facade
+        .validate_ocafile(ocafile.to_string())
+        .unwrap()
+        .oca_bundle;
+    let schema = Schema::from_bundle(&facade, &bundle).unwrap();
+
+    let records: Vec<Value> = serde_json::from_str(
+        r#"[
+        {"name": "Anna", "age": 30, "address": {"street": "Main"}, "tags": ["a"], "level": "low"},
+        {"age": "old", "address": {"street": 5}, "tags": [], "level": "medium", "extra": 1}
+    ]"#,
+    )?;
+    let errors = validate_records(&schema, &records);
+    let fields = errors
+        .iter()
+        .map(|e| (e.record, e.field.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        vec![
+            (2, "address.street"),
+            (2, "age"),
+            (2, "level"),
+            (2, "name"),
+            (2, "tags"),
+            (2, "extra")
+        ]
+    );
+
+    let csv = "name,age,address.street,tags,level\nAnna,30,\"Main, 1\",\"[\"\"a\"\"]\",high\n";
+    let path = tmp_dir.path().join("records.csv");
+    fs::write(&path, csv)?;
+    let records = read_records(&path, &schema).unwrap();
+    assert_eq!(records[0]["address"]["street"], "Main, 1");
+    assert_eq!(records[0]["age"], 30.0);
+    assert!(validate_records(&schema, &records).is_empty());
+
+    Ok(())
+}
@@ -429,7 +429,13 @@ impl oca_rs::facade::build::References for DependencyGraph {
     }
 
     fn save(&mut self, refn: &str, value: String) {
-        self.update_said(refn, value.parse().unwrap()).unwrap();
+        let result = value
+            .parse()
+            .map_err(|_| GraphError::UnknownSaid(refn.to_string()))
+            .and_then(|said| self.update_said(refn, said));
+        if let Err(e) = result {
+            warn!("Can't save SAID of {}: {}", refn, e);
+        }
     }
 }
 
@@ -0,0 +1,300 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use oca_rs::facade::build::ValidationError;
+use serde_json::Value;
+
+use crate::error::CliError;
+
+/// Hints shown for errors with message containing given phrase. The first
+/// matching phrase wins.
+const HINTS: &[(&str, &str)] = &[
+    (
+        "expected _attr_type",
+        "attribute type must be one of `Text`, `Numeric`, `Boolean`, `DateTime`, `Binary`, `Array[<type>]` or `refn:<name>`",
+    ),
+    (
+        "Undefined attribute",
+        "attributes must be added with `ADD ATTRIBUTE` before overlays use them",
+    ),
+    (
+        "Reference",
+        "build the referenced ocafile first, or check its `-- name=` line",
+    ),
+    ("language", "use ISO 639-1 language code, e.g. `en`"),
+    (
+        "parsing error: expected",
+        "check syntax of the command, e.g. `ADD LABEL en ATTRS name=\"Name\"`",
+    ),
+];
+
+/// Error in ocafile, rendered in the style of compiler diagnostics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub message: String,
+    /// Line and column, starting from 1.
+    pub location: Option<(usize, usize)>,
+    /// Line of ocafile the error points to.
+    pub source_line: Option<String>,
+    /// Number of characters to underline.
+    pub span: usize,
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    /// Builds diagnostic from fields of facade error: line number (`ln`),
+    /// column (`col`, only for grammar errors), raw line (`c`) and message
+    /// (`e`). Errors without column point to the token named in message, or
+    /// to the whole line.
+    pub fn new(path: &Path, source: Option<&str>, error: &ValidationError) -> Self {
+        let fields = serde_json::to_value(error).unwrap_or_default();
+        let line_number = fields.get("ln").and_then(Value::as_u64);
+        let (message, location, source_line) = match line_number {
+            Some(line_number) => {
+                let line_number = line_number as usize;
+                let raw_line = fields.get("c").and_then(Value::as_str).unwrap_or_default();
+                let message = fields
+                    .get("e")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let source_line = source
+                    .and_then(|source| source.lines().nth(line_number.saturating_sub(1)))
+                    .unwrap_or_else(|| raw_line.trim_end_matches('␊'))
+                    .to_string();
+                let (column, span) = match fields.get("col").and_then(Value::as_u64) {
+                    Some(column) => {
+                        let column = column as usize;
+                        let token = source_line
+                            .chars()
+                            .skip(column.saturating_sub(1))
+                            .take_while(|c| !c.is_whitespace())
+                            .count();
+                        (column, token.max(1))
+                    }
+                    None => locate_token(&source_line, &message),
+                };
+                (
+                    message,
+                    Some((line_number, column, span)),
+                    Some(source_line),
+                )
+            }
+            None => {
+                let location = match error {
+                    ValidationError::UnknownRefn(refn) => {
+                        source.and_then(|source| find(source, &format!("refn:{}", refn)))
+                    }
+                    _ => None,
+                };
+                let source_line = location.and_then(|(line, _, _)| {
+                    source
+                        .and_then(|source| source.lines().nth(line - 1))
+                        .map(str::to_string)
+                });
+                (error.to_string(), location, source_line)
+            }
+        };
+        let hint = HINTS
+            .iter()
+            .find(|(phrase, _)| message.contains(phrase))
+            .map(|(_, hint)| hint.to_string());
+        Self {
+            path: path.to_path_buf(),
+            message,
+            location: location.map(|(line, column, _)| (line, column)),
+            source_line,
+            span: location.map(|(_, _, span)| span).unwrap_or(1),
+            hint,
+        }
+    }
+
+    /// Reads ocafile and returns diagnostics of all its errors.
+    pub fn from_file<'a>(
+        path: &Path,
+        errors: impl IntoIterator<Item = &'a ValidationError>,
+    ) -> Vec<Self> {
+        let source = fs::read_to_string(path).ok();
+        errors
+            .into_iter()
+            .map(|error| Self::new(path, source.as_deref(), error))
+            .collect()
+    }
+
+    /// Returns `file:line:col`, or only file if location is unknown.
+    pub fn position(&self) -> String {
+        match self.location {
+            Some((line, column)) => format!("{}:{}:{}", self.path.display(), line, column),
+            None => self.path.display().to_string(),
+        }
+    }
+
+    /// Returns lines of rendered diagnostic.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("error: {}", self.message)];
+        let gutter = self
+            .location
+            .map(|(line, _)| line.to_string().len())
+            .unwrap_or(1);
+        let pad = " ".repeat(gutter);
+        lines.push(format!("{}--> {}", pad, self.position()));
+        if let (Some((line, column)), Some(source_line)) = (self.location, &self.source_line) {
+            lines.push(format!("{} |", pad));
+            lines.push(format!("{} | {}", line, source_line));
+            lines.push(format!(
+                "{} | {}{}",
+                pad,
+                " ".repeat(column.saturating_sub(1)),
+                "^".repeat(self.span)
+            ));
+        }
+        if let Some(hint) = &self.hint {
+            lines.push(format!("{} |", pad));
+            lines.push(format!("{} = hint: {}", pad, hint));
+        }
+        lines
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.lines().join("\n"))
+    }
+}
+
+/// Renders diagnostics of errors found in ocafile, separated by empty lines.
+/// Returns `None` for errors not related to content of ocafile.
+pub fn render(error: &CliError) -> Option<String> {
+    let diagnostics = match error {
+        CliError::GrammarError(path, errors) => Diagnostic::from_file(path, errors),
+        CliError::BuildingError(path, errors) => {
+            Diagnostic::from_file(path, errors.validation_errors())
+        }
+        _ => return None,
+    };
+    Some(
+        diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    )
+}
+
+/// Returns column and length of token named after the last `: ` of message,
+/// e.g. attribute in `Undefined attribute: name`. Falls back to the whole
+/// line without indentation.
+fn locate_token(line: &str, message: &str) -> (usize, usize) {
+    message
+        .rsplit_once(": ")
+        .map(|(_, token)| token.trim())
+        .filter(|token| !token.is_empty())
+        .and_then(|token| {
+            line.match_indices(token).find_map(|(byte, _)| {
+                let before = line[..byte].chars().next_back();
+                let after = line[byte + token.len()..].chars().next();
+                let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+                (!is_word(before) && !is_word(after))
+                    .then(|| (line[..byte].chars().count() + 1, token.chars().count()))
+            })
+        })
+        .unwrap_or_else(|| {
+            let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+            (indent + 1, line.trim().chars().count().max(1))
+        })
+}
+
+/// Returns line, column and length of the first occurrence of `text`.
+fn find(source: &str, text: &str) -> Option<(usize, usize, usize)> {
+    source.lines().enumerate().find_map(|(i, line)| {
+        line.find(text).map(|byte| {
+            (
+                i + 1,
+                line[..byte].chars().count() + 1,
+                text.chars().count(),
+            )
+        })
+    })
+}
+
+#[cfg(test)]
+fn validation_errors(source: &str) -> Vec<ValidationError> {
+    let tmp_dir = tempdir::TempDir::new("diagnostic").unwrap();
+    let mut facade = crate::get_oca_facade(tmp_dir.path().to_path_buf());
+    facade.validate_ocafile(source.to_string()).unwrap_err()
+}
+
+#[test]
+fn test_grammar_error() {
+    let source = "-- name=person\nADD ATTRIBUTE name=Txt\n";
+    let errors = validation_errors(source);
+    let diagnostic = Diagnostic::new(Path::new("person.ocafile"), Some(source), &errors[0]);
+    assert_eq!(
+        diagnostic.lines(),
+        vec![
+            "error: parsing error: expected _attr_type",
+            " --> person.ocafile:2:20",
+            "  |",
+            "2 | ADD ATTRIBUTE name=Txt",
+            "  |                    ^^^",
+            "  |",
+            "  = hint: attribute type must be one of `Text`, `Numeric`, `Boolean`, `DateTime`, `Binary`, `Array[<type>]` or `refn:<name>`",
+        ]
+    );
+
+    // Missing type is reported after `=`, not at the start of line.
+    let source = "-- name=person\nADD ATTRIBUTE x=\n";
+    let errors = validation_errors(source);
+    let diagnostic = Diagnostic::new(Path::new("person.ocafile"), Some(source), &errors[0]);
+    assert_eq!(diagnostic.location, Some((2, 17)));
+    assert_eq!(diagnostic.source_line.as_deref(), Some("ADD ATTRIBUTE x="));
+    assert!(diagnostic.hint.unwrap().starts_with("attribute type"));
+
+    let source = "-- name=person\nADD ATTRIBUTE name=Text\nADDD LABEL en ATTRS name=\"N\"\n";
+    let errors = validation_errors(source);
+    let diagnostic = Diagnostic::new(Path::new("person.ocafile"), Some(source), &errors[0]);
+    assert_eq!(diagnostic.location, Some((3, 4)));
+    assert!(diagnostic.hint.unwrap().starts_with("check syntax"));
+}
+
+#[test]
+fn test_building_error() {
+    let source = "-- name=person\nADD ATTRIBUTE name=Text\nADD LABEL en ATTRS nme=\"Name\"\n";
+    let errors = validation_errors(source);
+    let diagnostic = Diagnostic::new(Path::new("person.ocafile"), None, &errors[0]);
+    assert_eq!(
+        diagnostic.lines(),
+        vec![
+            "error: Undefined attribute: nme",
+            " --> person.ocafile:3:20",
+            "  |",
+            "3 | ADD LABEL en ATTRS nme=\"Name\"",
+            "  |                    ^^^",
+            "  |",
+            "  = hint: attributes must be added with `ADD ATTRIBUTE` before overlays use them",
+        ]
+    );
+
+    let source = "-- name=person\nADD ATTRIBUTE addr=refn:missing\n";
+    let errors = validation_errors(source);
+    let diagnostic = Diagnostic::new(Path::new("person.ocafile"), Some(source), &errors[0]);
+    assert_eq!(diagnostic.message, "Reference missing not found");
+    assert_eq!(diagnostic.location, Some((2, 20)));
+    assert_eq!(diagnostic.span, 12);
+    assert!(diagnostic.hint.unwrap().starts_with("build the referenced"));
+
+    let diagnostic = Diagnostic::new(Path::new("person.ocafile"), None, &errors[0]);
+    assert_eq!(
+        diagnostic.lines(),
+        vec![
+            "error: Reference missing not found",
+            " --> person.ocafile",
+            "  |",
+            "  = hint: build the referenced ocafile first, or check its `-- name=` line",
+        ]
+    );
+}
@@ -0,0 +1,513 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Display,
+};
+
+use oca_bundle_semantics::state::oca::OCABundle;
+use oca_rs::Facade;
+use said::SelfAddressingIdentifier;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    coverage::normalize_language, error::CliError, list::attribute_type_name, utils::reference_said,
+};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default)]
+pub enum DiffFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    AttributeAdded {
+        attribute: String,
+        attribute_type: String,
+    },
+    AttributeRemoved {
+        attribute: String,
+        attribute_type: String,
+    },
+    AttributeTypeChanged {
+        attribute: String,
+        from: String,
+        to: String,
+    },
+    FlaggedAdded {
+        attribute: String,
+    },
+    FlaggedRemoved {
+        attribute: String,
+    },
+    ClassificationChanged {
+        from: String,
+        to: String,
+    },
+    OverlayAdded {
+        overlay: String,
+        language: Option<String>,
+    },
+    OverlayRemoved {
+        overlay: String,
+        language: Option<String>,
+    },
+    /// Value of overlay field changed. For fields keyed by attribute name,
+    /// `key` is the attribute.
+    OverlayChanged {
+        overlay: String,
+        language: Option<String>,
+        field: String,
+        key: Option<String>,
+        from: Option<Value>,
+        to: Option<Value>,
+    },
+}
+
+fn overlay_name(overlay: &str, language: &Option<String>) -> String {
+    match language {
+        Some(language) => format!("{} ({})", overlay, language),
+        None => overlay.to_string(),
+    }
+}
+
+fn value_or_none(value: &Option<Value>) -> String {
+    value
+        .as_ref()
+        .map(ToString::to_string)
+        .unwrap_or_else(|| "none".to_string())
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::AttributeAdded {
+                attribute,
+                attribute_type,
+            } => write!(f, "+ attribute {}: {}", attribute, attribute_type),
+            Change::AttributeRemoved {
+                attribute,
+                attribute_type,
+            } => write!(f, "- attribute {}: {}", attribute, attribute_type),
+            Change::AttributeTypeChanged {
+                attribute,
+                from,
+                to,
+            } => write!(f, "~ attribute {}: {} -> {}", attribute, from, to),
+            Change::FlaggedAdded { attribute } => write!(f, "+ flagged {}", attribute),
+            Change::FlaggedRemoved { attribute } => write!(f, "- flagged {}", attribute),
+            Change::ClassificationChanged { from, to } => {
+                write!(f, "~ classification: {:?} -> {:?}", from, to)
+            }
+            Change::OverlayAdded { overlay, language } => {
+                write!(f, "+ overlay {}", overlay_name(overlay, language))
+            }
+            Change::OverlayRemoved { overlay, language } => {
+                write!(f, "- overlay {}", overlay_name(overlay, language))
+            }
+            Change::OverlayChanged {
+                overlay,
+                language,
+                field,
+                key,
+                from,
+                to,
+            } => {
+                let field = match key {
+                    Some(key) => format!("{}.{}", field, key),
+                    None => field.clone(),
+                };
+                let sign = match (from, to) {
+                    (None, _) => '+',
+                    (_, None) => '-',
+                    _ => '~',
+                };
+                write!(
+                    f,
+                    "{} {} {}: {} -> {}",
+                    sign,
+                    overlay_name(overlay, language),
+                    field,
+                    value_or_none(from),
+                    value_or_none(to)
+                )
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct BundleDiff {
+    pub from: String,
+    pub to: String,
+    pub changes: Vec<Change>,
+    /// Diffs of referenced OCA bundles, by attribute name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub references: BTreeMap<String, BundleDiff>,
+}
+
+impl BundleDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.references.values().all(BundleDiff::is_empty)
+    }
+
+    fn lines(&self, indent: usize, out: &mut Vec<String>) {
+        let pad = " ".repeat(indent);
+        for change in &self.changes {
+            out.push(format!("{}{}", pad, change));
+        }
+        for (attribute, diff) in &self.references {
+            if diff.is_empty() {
+                continue;
+            }
+            out.push(format!(
+                "{}~ reference {}: {} -> {}",
+                pad, attribute, diff.from, diff.to
+            ));
+            diff.lines(indent + 4, out);
+        }
+    }
+
+    pub fn format(&self, format: DiffFormat) -> String {
+        match format {
+            DiffFormat::Json => serde_json::to_string_pretty(self).unwrap(),
+            DiffFormat::Text => {
+                let mut out = vec![format!("diff {} {}", self.from, self.to)];
+                if self.is_empty() {
+                    out.push("No changes".to_string());
+                }
+                self.lines(0, &mut out);
+                out.join("\n")
+            }
+        }
+    }
+}
+
+/// Compares two OCA bundles from local repository, including bundles they
+/// reference.
+pub fn diff(
+    facade: &Facade,
+    from: &SelfAddressingIdentifier,
+    to: &SelfAddressingIdentifier,
+) -> Result<BundleDiff, CliError> {
+    let refs = facade.fetch_all_refs().unwrap_or_default();
+    let mut visited = HashSet::new();
+    diff_saids(facade, &refs, from, to, &mut visited)
+}
+
+fn load(facade: &Facade, said: &SelfAddressingIdentifier) -> Result<OCABundle, CliError> {
+    facade
+        .get_oca_bundle(said.clone(), false)
+        .map(|bundle| bundle.bundle)
+        .map_err(|_| CliError::OCABundleSAIDNotFound(said.clone()))
+}
+
+fn diff_saids(
+    facade: &Facade,
+    refs: &HashMap<String, String>,
+    from: &SelfAddressingIdentifier,
+    to: &SelfAddressingIdentifier,
+    visited: &mut HashSet<(String, String)>,
+) -> Result<BundleDiff, CliError> {
+    visited.insert((from.to_string(), to.to_string()));
+    let old = load(facade, from)?;
+    let new = load(facade, to)?;
+    let mut diff = diff_bundles(&old, &new);
+
+    for (attribute, old_type) in &old.capture_base.attributes {
+        let new_type = match new.capture_base.attributes.get(attribute) {
+            Some(new_type) => new_type,
+            None => continue,
+        };
+        let (old_said, new_said) = match (
+            reference_said(old_type, refs),
+            reference_said(new_type, refs),
+        ) {
+            (Some(old_said), Some(new_said)) if old_said != new_said => (old_said, new_said),
+            _ => continue,
+        };
+        if visited.contains(&(old_said.to_string(), new_said.to_string())) {
+            continue;
+        }
+        let nested = diff_saids(facade, refs, &old_said, &new_said, visited)?;
+        diff.references.insert(attribute.clone(), nested);
+    }
+    Ok(diff)
+}
+
+/// Compares capture bases and overlays of two bundles, without following
+/// references.
+pub fn diff_bundles(old: &OCABundle, new: &OCABundle) -> BundleDiff {
+    let said = |bundle: &OCABundle| {
+        bundle
+            .said
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default()
+    };
+    let mut changes = vec![];
+
+    let old_attributes = &old.capture_base.attributes;
+    let new_attributes = &new.capture_base.attributes;
+    for (attribute, attr) in old_attributes {
+        match new_attributes.get(attribute) {
+            None => changes.push(Change::AttributeRemoved {
+                attribute: attribute.clone(),
+                attribute_type: attribute_type_name(attr),
+            }),
+            Some(new_attr) => {
+                let (from, to) = (attribute_type_name(attr), attribute_type_name(new_attr));
+                if from != to {
+                    changes.push(Change::AttributeTypeChanged {
+                        attribute: attribute.clone(),
+                        from,
+                        to,
+                    });
+                }
+            }
+        }
+    }
+    for (attribute, attr) in new_attributes {
+        if !old_attributes.contains_key(attribute) {
+            changes.push(Change::AttributeAdded {
+                attribute: attribute.clone(),
+                attribute_type: attribute_type_name(attr),
+            });
+        }
+    }
+
+    let old_flagged = &old.capture_base.flagged_attributes;
+    let new_flagged = &new.capture_base.flagged_attributes;
+    changes.extend(
+        new_flagged
+            .iter()
+            .filter(|attribute| !old_flagged.contains(attribute))
+            .map(|attribute| Change::FlaggedAdded {
+                attribute: attribute.clone(),
+            }),
+    );
+    changes.extend(
+        old_flagged
+            .iter()
+            .filter(|attribute| !new_flagged.contains(attribute))
+            .map(|attribute| Change::FlaggedRemoved {
+                attribute: attribute.clone(),
+            }),
+    );
+
+    if old.capture_base.classification != new.capture_base.classification {
+        changes.push(Change::ClassificationChanged {
+            from: old.capture_base.classification.clone(),
+            to: new.capture_base.classification.clone(),
+        });
+    }
+
+    changes.extend(diff_overlays(&overlay_fields(old), &overlay_fields(new)));
+
+    BundleDiff {
+        from: said(old),
+        to: said(new),
+        changes,
+        references: BTreeMap::new(),
+    }
+}
+
+/// Overlay type and language.
+pub(crate) type OverlayKey = (String, Option<String>);
+
+/// Returns overlays of bundle as JSON objects, by overlay type and language.
+pub(crate) fn overlay_fields(
+    bundle: &OCABundle,
+) -> BTreeMap<OverlayKey, serde_json::Map<String, Value>> {
+    let mut out = BTreeMap::new();
+    let value = serde_json::to_value(bundle).unwrap_or_default();
+    // Overlays are serialized either as a list or as a map by overlay name.
+    let overlays: Vec<&Value> = match value.get("overlays") {
+        Some(Value::Array(overlays)) => overlays.iter().collect(),
+        Some(Value::Object(overlays)) => overlays
+            .values()
+            .flat_map(|value| match value {
+                Value::Array(overlays) => overlays.iter().collect::<Vec<_>>(),
+                overlay => vec![overlay],
+            })
+            .collect(),
+        _ => vec![],
+    };
+    for overlay in overlays {
+        let mut fields = match overlay {
+            Value::Object(fields) => fields.clone(),
+            _ => continue,
+        };
+        let overlay_type = fields
+            .remove("type")
+            .and_then(|t| t.as_str().map(short_overlay_type))
+            .unwrap_or_default();
+        let language = fields
+            .remove("language")
+            .and_then(|l| l.as_str().map(str::to_string));
+        for ignored in ["d", "capture_base"] {
+            fields.remove(ignored);
+        }
+        out.insert((overlay_type, language), fields);
+    }
+    out
+}
+
+/// Returns values of overlay of given type, by attribute. With `language`,
+/// only overlays in that language or without language are used.
+pub(crate) fn overlay_values(
+    overlays: &BTreeMap<OverlayKey, serde_json::Map<String, Value>>,
+    overlay_type: &str,
+    language: Option<&str>,
+) -> BTreeMap<String, Value> {
+    overlays
+        .iter()
+        .filter(|((t, l), _)| {
+            t == overlay_type
+                && match (language, l) {
+                    (Some(language), Some(l)) => normalize_language(l).as_deref() == Some(language),
+                    _ => true,
+                }
+        })
+        .flat_map(|(_, fields)| fields.values())
+        .filter_map(Value::as_object)
+        .flat_map(|values| values.clone())
+        .collect()
+}
+
+/// Returns attributes that overlay has values for. Values are kept in object
+/// fields, such as `attribute_labels`.
+pub(crate) fn overlay_attributes(
+    fields: &serde_json::Map<String, Value>,
+) -> impl Iterator<Item = &str> {
+    fields
+        .values()
+        .filter_map(Value::as_object)
+        .flat_map(|values| values.keys().map(String::as_str))
+}
+
+/// Turns overlay type such as `spec/overlays/label/1.0` into `label`.
+pub(crate) fn short_overlay_type(overlay_type: &str) -> String {
+    overlay_type
+        .strip_prefix("spec/overlays/")
+        .and_then(|rest| rest.split('/').next())
+        .unwrap_or(overlay_type)
+        .to_string()
+}
+
+fn diff_overlays(
+    old: &BTreeMap<OverlayKey, serde_json::Map<String, Value>>,
+    new: &BTreeMap<OverlayKey, serde_json::Map<String, Value>>,
+) -> Vec<Change> {
+    let mut changes = vec![];
+    for ((overlay, language), old_fields) in old {
+        let new_fields = match new.get(&(overlay.clone(), language.clone())) {
+            Some(new_fields) => new_fields,
+            None => {
+                changes.push(Change::OverlayRemoved {
+                    overlay: overlay.clone(),
+                    language: language.clone(),
+                });
+                continue;
+            }
+        };
+        let changed = |field: &str, key: Option<&String>, from, to| Change::OverlayChanged {
+            overlay: overlay.clone(),
+            language: language.clone(),
+            field: field.to_string(),
+            key: key.cloned(),
+            from,
+            to,
+        };
+        let fields = old_fields
+            .keys()
+            .chain(new_fields.keys())
+            .collect::<BTreeSet<_>>();
+        for field in fields {
+            match (old_fields.get(field), new_fields.get(field)) {
+                (Some(Value::Object(old_map)), Some(Value::Object(new_map))) => {
+                    let keys = old_map
+                        .keys()
+                        .chain(new_map.keys())
+                        .collect::<BTreeSet<_>>();
+                    for key in keys {
+                        let (from, to) = (old_map.get(key), new_map.get(key));
+                        if from != to {
+                            changes.push(changed(field, Some(key), from.cloned(), to.cloned()));
+                        }
+                    }
+                }
+                (from, to) if from != to => {
+                    changes.push(changed(field, None, from.cloned(), to.cloned()))
+                }
+                _ => {}
+            }
+        }
+    }
+    for (overlay, language) in new.keys() {
+        if !old.contains_key(&(overlay.clone(), language.clone())) {
+            changes.push(Change::OverlayAdded {
+                overlay: overlay.clone(),
+                language: language.clone(),
+            });
+        }
+    }
+    changes
+}
+
+#[test]
+fn test_diff() -> anyhow::Result<()> {
+    use oca_rs::facade::bundle::BundleElement;
+
+    let tmp_dir = tempdir::TempDir::new("diff")?;
+    let mut facade = crate::get_oca_facade(tmp_dir.path().to_path_buf());
+    let mut build = |ocafile: &str| -> SelfAddressingIdentifier {
+        match facade.build_from_ocafile(ocafile.to_string()).unwrap() {
+            BundleElement::Mechanics(bundle) => bundle.said.unwrap(),
+            BundleElement::Transformation(_) => unreachable!(),
+        }
+    };
+    let address_v1 = build("ADD ATTRIBUTE street=Text");
+    let address_v2 = build("ADD ATTRIBUTE street=Text city=Text");
+    let old = build(&format!(
+        "ADD ATTRIBUTE name=Text age=Text address=refs:{}\nADD LABEL en ATTRS name=\"Name\"",
+        address_v1
+    ));
+    let new = build(&format!(
+        "ADD ATTRIBUTE name=Text age=Numeric email=Text address=refs:{}\nADD LABEL en ATTRS name=\"Full name\"\nADD LABEL fr ATTRS name=\"Nom\"",
+        address_v2
+    ));
+
+    let diff = diff(&facade, &old, &new).unwrap();
+    assert!(diff.changes.contains(&Change::AttributeAdded {
+        attribute: "email".to_string(),
+        attribute_type: "Text".to_string(),
+    }));
+    assert!(diff.changes.contains(&Change::AttributeTypeChanged {
+        attribute: "age".to_string(),
+        from: "Text".to_string(),
+        to: "Numeric".to_string(),
+    }));
+    assert!(diff.changes.iter().any(|change| matches!(
+        change,
+        Change::OverlayChanged { overlay, key: Some(key), .. } if overlay == "label" && key == "name"
+    )));
+    assert!(diff.changes.iter().any(|change| matches!(
+        change,
+        Change::OverlayAdded { overlay, language: Some(_) } if overlay == "label"
+    )));
+    assert_eq!(
+        diff.references["address"].changes,
+        vec![Change::AttributeAdded {
+            attribute: "city".to_string(),
+            attribute_type: "Text".to_string(),
+        }]
+    );
+
+    let same = crate::diff::diff(&facade, &old, &old).unwrap();
+    assert!(same.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,421 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    fs,
+    panic::AssertUnwindSafe,
+    path::Path,
+};
+
+use oca_ast_semantics::ast::RefValue;
+use oca_bundle_semantics::state::oca::OCABundle;
+use oca_rs::{
+    data_storage::{DataStorage, Namespace},
+    facade::bundle::BundleElement,
+    Facade,
+};
+use said::SelfAddressingIdentifier;
+
+use crate::{
+    config::{create_or_open_local_storage, OCA_CACHE_DB_DIR, OCA_INDEX_DIR},
+    error::{BuildingFailures, CliError},
+    get_oca_facade,
+    publish::batches,
+    utils::{attribute_reference, handle_panic, reference_said},
+};
+
+#[derive(Debug)]
+pub enum Problem {
+    /// Stored bundle can't be read or its ocafile can't be rebuilt.
+    InvalidBundle { key: String, reason: String },
+    /// SAID derived from stored bundle doesn't match the key it is stored under.
+    SaidMismatch { key: String, actual: String },
+    /// Name points to bundle that doesn't exist.
+    DanglingRef { refn: String, said: String },
+    /// Bundle references bundle that doesn't exist.
+    MissingDependency { said: String, dependency: String },
+    /// Bundle is missing in SQLite index.
+    MissingInIndex(String),
+    /// SQLite index lists bundle that doesn't exist.
+    UnknownInIndex(String),
+    /// SQLite index can't be read.
+    BrokenIndex(String),
+}
+
+impl Problem {
+    /// Returns true if problem is fixed by rebuilding SQLite index.
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            Problem::MissingInIndex(_) | Problem::UnknownInIndex(_) | Problem::BrokenIndex(_)
+        )
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::InvalidBundle { key, reason } => {
+                write!(f, "OCA bundle {} is invalid: {}", key, reason)
+            }
+            Problem::SaidMismatch { key, actual } => {
+                write!(f, "OCA bundle stored under {} has SAID {}", key, actual)
+            }
+            Problem::DanglingRef { refn, said } => {
+                write!(f, "Name {} points to missing OCA bundle {}", refn, said)
+            }
+            Problem::MissingDependency { said, dependency } => write!(
+                f,
+                "OCA bundle {} depends on missing OCA bundle {}",
+                said, dependency
+            ),
+            Problem::MissingInIndex(said) => write!(f, "OCA bundle {} is missing in index", said),
+            Problem::UnknownInIndex(said) => {
+                write!(f, "Index lists OCA bundle {} that doesn't exist", said)
+            }
+            Problem::BrokenIndex(reason) => write!(f, "Index can't be read: {}", reason),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Number of bundles found in store.
+    pub bundles: usize,
+    pub problems: Vec<Problem>,
+    /// Direct dependencies of valid bundles, by SAID.
+    dependencies: HashMap<String, Vec<String>>,
+    /// Names of bundles, by SAID.
+    names: HashMap<String, Vec<String>>,
+}
+
+impl Report {
+    pub fn print(&self) {
+        for problem in &self.problems {
+            println!("{}", problem);
+        }
+        println!(
+            "Checked {} OCA bundles: {} problems found",
+            self.bundles,
+            self.problems.len()
+        );
+        let repairable = self.problems.iter().filter(|p| p.is_repairable()).count();
+        if repairable > 0 {
+            println!(
+                "{} of them can be fixed with `oca doctor --repair`",
+                repairable
+            );
+        }
+    }
+}
+
+/// Checks that stored bundles, refs and SQLite index are consistent.
+pub fn check(local_repository_path: &Path) -> Result<Report, CliError> {
+    let (stored, mut facade) = open_store(local_repository_path)?;
+    let refs = facade.fetch_all_refs().unwrap_or_default();
+
+    let mut report = Report {
+        bundles: stored.len(),
+        names: names_by_said(&refs),
+        ..Default::default()
+    };
+
+    let mut keys = stored.keys().collect::<Vec<_>>();
+    keys.sort();
+    for key in keys {
+        match check_bundle(&mut facade, key, &stored[key]) {
+            Ok(bundle) => {
+                let mut dependencies = vec![];
+                for attr in bundle.capture_base.attributes.values() {
+                    let dependency = match attribute_reference(attr) {
+                        Some(RefValue::Said(said)) => said.to_string(),
+                        Some(RefValue::Name(refn)) => match refs.get(refn) {
+                            Some(said) => said.clone(),
+                            None => format!("refn:{}", refn),
+                        },
+                        None => continue,
+                    };
+                    if stored.contains_key(&dependency) {
+                        dependencies.push(dependency);
+                    } else {
+                        report.problems.push(Problem::MissingDependency {
+                            said: key.clone(),
+                            dependency,
+                        });
+                    }
+                }
+                report.dependencies.insert(key.clone(), dependencies);
+            }
+            Err(problem) => report.problems.push(problem),
+        }
+    }
+
+    let refs = refs.into_iter().collect::<BTreeMap<_, _>>();
+    for (refn, said) in refs {
+        if !stored.contains_key(&said) {
+            report.problems.push(Problem::DanglingRef { refn, said });
+        }
+    }
+
+    match indexed_saids(&facade) {
+        Ok(indexed) => {
+            let mut missing = stored
+                .keys()
+                .filter(|key| !indexed.contains(*key))
+                .cloned()
+                .collect::<Vec<_>>();
+            missing.sort();
+            report
+                .problems
+                .extend(missing.into_iter().map(Problem::MissingInIndex));
+            let mut unknown = indexed
+                .into_iter()
+                .filter(|said| !stored.contains_key(said))
+                .collect::<Vec<_>>();
+            unknown.sort();
+            report
+                .problems
+                .extend(unknown.into_iter().map(Problem::UnknownInIndex));
+        }
+        Err(reason) => report.problems.push(Problem::BrokenIndex(reason)),
+    }
+
+    Ok(report)
+}
+
+/// Reads stored bundle and rebuilds it from its ocafile, to check if SAIDs
+/// match.
+fn check_bundle(facade: &mut Facade, key: &str, json: &[u8]) -> Result<OCABundle, Problem> {
+    let invalid = |reason: String| Problem::InvalidBundle {
+        key: key.to_string(),
+        reason,
+    };
+    let bundle: OCABundle = serde_json::from_slice(json).map_err(|e| invalid(e.to_string()))?;
+    let stored_said = bundle.said.as_ref().map(ToString::to_string);
+    if stored_said.as_deref() != Some(key) {
+        return Err(Problem::SaidMismatch {
+            key: key.to_string(),
+            actual: stored_said.unwrap_or_default(),
+        });
+    }
+
+    let said: SelfAddressingIdentifier = key.parse().map_err(|e| invalid(format!("{}", e)))?;
+    let derived = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        let ocafile = facade
+            .get_oca_bundle_ocafile(said, true)
+            .map_err(|e| e.join(", "))?;
+        facade
+            .validate_ocafile(ocafile)
+            .map(|oca_build| oca_build.oca_bundle.said)
+            .map_err(|e| format!("{:?}", e))
+    }))
+    .map_err(|panic| invalid(handle_panic(panic).to_string()))?
+    .map_err(invalid)?;
+
+    match derived.map(|said| said.to_string()) {
+        Some(derived) if derived == key => Ok(bundle),
+        derived => Err(Problem::SaidMismatch {
+            key: key.to_string(),
+            actual: derived.unwrap_or_default(),
+        }),
+    }
+}
+
+/// Returns SAIDs of bundles that attributes of bundle refer to. References by
+/// name are resolved with `refs`, unknown names are skipped.
+pub(crate) fn bundle_dependencies(
+    bundle: &OCABundle,
+    refs: &HashMap<String, String>,
+) -> Vec<String> {
+    bundle
+        .capture_base
+        .attributes
+        .values()
+        .filter_map(|attr| reference_said(attr, refs))
+        .map(|said| said.to_string())
+        .collect()
+}
+
+/// Returns SAIDs of all bundles listed in SQLite index.
+fn indexed_saids(facade: &Facade) -> Result<HashSet<String>, String> {
+    std::panic::catch_unwind(AssertUnwindSafe(|| {
+        let page_size = 100;
+        let mut page = 1;
+        let mut saids = HashSet::new();
+        loop {
+            let result = facade
+                .fetch_all_oca_bundle(page_size, page)
+                .map_err(|e| e.join(", "))?;
+            let count = result.records.len();
+            saids.extend(
+                result
+                    .records
+                    .into_iter()
+                    .filter_map(|bundle| bundle.said.map(|said| said.to_string())),
+            );
+            if count == 0 || saids.len() >= result.metadata.total {
+                return Ok(saids);
+            }
+            page += 1;
+        }
+    }))
+    .map_err(|panic| handle_panic(panic).to_string())?
+}
+
+/// Returns JSON of stored bundles, by SAID, and facade of local repository.
+/// Sled store is locked by facade, so it is read before facade is created.
+pub(crate) fn open_store(
+    local_repository_path: &Path,
+) -> Result<(HashMap<String, Vec<u8>>, Facade), CliError> {
+    let stored = {
+        let db = create_or_open_local_storage(local_repository_path.join(OCA_CACHE_DB_DIR));
+        db.get_all(Namespace::OCABundlesJSON)
+            .map_err(CliError::StorageError)?
+    };
+    Ok((stored, get_oca_facade(local_repository_path.to_path_buf())))
+}
+
+/// Returns sorted names (refn) of bundles, by SAID.
+pub(crate) fn names_by_said(refs: &HashMap<String, String>) -> HashMap<String, Vec<String>> {
+    let mut names: HashMap<String, Vec<String>> = HashMap::new();
+    for (refn, said) in refs {
+        names.entry(said.clone()).or_default().push(refn.clone());
+    }
+    names.values_mut().for_each(|refns| refns.sort());
+    names
+}
+
+/// Removes SQLite index and fills it again by rebuilding all valid bundles
+/// in order of dependencies. Returns number of rebuilt bundles.
+pub fn repair(local_repository_path: &Path, report: &Report) -> Result<usize, CliError> {
+    let index_path = local_repository_path.join(OCA_INDEX_DIR);
+    if index_path.is_dir() {
+        fs::remove_dir_all(&index_path)?;
+    } else if index_path.exists() {
+        fs::remove_file(&index_path)?;
+    }
+
+    let mut facade = get_oca_facade(local_repository_path.to_path_buf());
+    let mut rebuilt = 0;
+    for said in batches(&report.dependencies)?.into_iter().flatten() {
+        let refns = report
+            .names
+            .get(&said)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let said: SelfAddressingIdentifier = said.parse()?;
+        let ocafile = facade
+            .get_oca_bundle_ocafile(said.clone(), true)
+            .map_err(CliError::OcaBundleAstError)?;
+        build_named(&mut facade, &said, &ocafile, refns)?;
+        rebuilt += 1;
+    }
+    Ok(rebuilt)
+}
+
+/// Checks that ocafile has expected SAID, then builds it once for every
+/// name, so that all of them point to the bundle. Nothing is stored and no
+/// name is moved if SAID doesn't match.
+pub(crate) fn build_named(
+    facade: &mut Facade,
+    said: &SelfAddressingIdentifier,
+    ocafile: &str,
+    refns: &[String],
+) -> Result<(), CliError> {
+    // Validated without name, so refn isn't saved for mismatching bundle.
+    let derived = facade
+        .validate_ocafile(ocafile.to_string())
+        .map_err(|errors| {
+            CliError::StorageError(format!(
+                "Validating OCA bundle of SAID {} failed: {}",
+                said,
+                errors
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })?
+        .oca_bundle
+        .said
+        .ok_or_else(|| {
+            CliError::StorageError(format!("OCA bundle of SAID {} has no SAID", said))
+        })?;
+    if &derived != said {
+        return Err(CliError::SaidMismatch {
+            expected: said.clone(),
+            actual: derived,
+        });
+    }
+    if refns.is_empty() {
+        return build_said(facade, said, ocafile.to_string());
+    }
+    for refn in refns {
+        build_said(facade, said, format!("-- name={}\n{}", refn, ocafile))?;
+    }
+    Ok(())
+}
+
+/// Builds ocafile and checks that the result has expected SAID.
+fn build_said(
+    facade: &mut Facade,
+    said: &SelfAddressingIdentifier,
+    ocafile: String,
+) -> Result<(), CliError> {
+    match facade.build_from_ocafile(ocafile) {
+        Ok(BundleElement::Mechanics(oca_bundle)) => {
+            let built_said = oca_bundle.said.ok_or_else(|| {
+                CliError::StorageError(format!("Rebuilt OCA bundle of SAID {} has no SAID", said))
+            })?;
+            if &built_said != said {
+                return Err(CliError::SaidMismatch {
+                    expected: said.clone(),
+                    actual: built_said,
+                });
+            }
+            Ok(())
+        }
+        Ok(BundleElement::Transformation(_)) => Err(CliError::StorageError(format!(
+            "Ocafile of SAID {} is not an OCA bundle",
+            said
+        ))),
+        Err(e) => Err(CliError::StorageError(format!(
+            "Rebuilding OCA bundle of SAID {} failed: {}",
+            said,
+            BuildingFailures::from(e)
+        ))),
+    }
+}
+
+#[test]
+fn test_check_and_repair() -> anyhow::Result<()> {
+    let tmp_dir = tempdir::TempDir::new("doctor")?;
+    let path = tmp_dir.path();
+    {
+        let mut facade = get_oca_facade(path.to_path_buf());
+        let ocafile = "-- name=first\nADD ATTRIBUTE name=Text".to_string();
+        facade.build_from_ocafile(ocafile).unwrap();
+        let ocafile = "-- name=second\nADD ATTRIBUTE first=refn:first".to_string();
+        facade.build_from_ocafile(ocafile).unwrap();
+        let ocafile = "-- name=alias\nADD ATTRIBUTE name=Text".to_string();
+        facade.build_from_ocafile(ocafile).unwrap();
+    }
+
+    let report = check(path).unwrap();
+    assert_eq!(report.bundles, 2);
+    assert!(report.problems.is_empty(), "{:?}", report.problems);
+
+    fs::remove_dir_all(path.join(OCA_INDEX_DIR))?;
+    let report = check(path).unwrap();
+    assert!(!report.problems.is_empty());
+    assert!(report.problems.iter().all(Problem::is_repairable));
+
+    assert_eq!(repair(path, &report).unwrap(), 2);
+    let report = check(path).unwrap();
+    assert!(report.problems.is_empty(), "{:?}", report.problems);
+    let refs = get_oca_facade(path.to_path_buf())
+        .fetch_all_refs()
+        .unwrap_or_default();
+    assert_eq!(refs["first"], refs["alias"]);
+
+    Ok(())
+}
@@ -5,14 +5,16 @@ use said::SelfAddressingIdentifier;
 use thiserror::Error;
 
 use crate::{
-    build::CacheError, dependency_graph::GraphError, presentation_command::PresentationError,
-    tui::bundle_list::BundleListError,
+    build::CacheError, config::ConfigError, dependency_graph::GraphError, history::HistoryError,
+    presentation_command::PresentationError, tui::bundle_list::BundleListError,
 };
 
 #[derive(Debug, Error)]
 pub enum CliError {
     #[error(transparent)]
     Input(#[from] io::Error),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
     #[error("Presentation command error: {0}")]
     Presentation(#[from] PresentationError),
     #[error("Error getting current directory: {0}")]
@@ -55,12 +57,63 @@ pub enum CliError {
     OCABundleSAIDNotFound(SelfAddressingIdentifier),
     #[error("Oca bundle of  refn {0} not found")]
     OCABundleRefnNotFound(String),
+    #[error("Oca bundle of said {0} has no name (refn)")]
+    MissingName(SelfAddressingIdentifier),
     #[error("Missing refn in file: {0}")]
     MissingRefn(PathBuf),
     #[error("Wrong repository url: {0}. Check `repository_url` in config file.")]
     UrlError(#[from] url::ParseError),
-    #[error("No repository path set. You can set it by adding `repository_url` to config file.")]
+    #[error("No repository path set. You can set it by adding `repository_url` or `[remotes.<name>]` to config file.")]
     UnknownRemoteRepoUrl,
+    #[error("Unknown remote: {0}. Check `remotes` in config file.")]
+    UnknownRemote(String),
+    #[error("Fetching error: said: {0}, reason: {1:?}")]
+    FetchError(SelfAddressingIdentifier, Vec<String>),
+    #[error("Pack error: {0}")]
+    PackError(String),
+    #[error("SAID mismatch: expected {expected}, got {actual}")]
+    SaidMismatch {
+        expected: SelfAddressingIdentifier,
+        actual: SelfAddressingIdentifier,
+    },
+    #[error("Invalid pattern: {0}")]
+    PatternError(#[from] regex::Error),
+    #[error("Refn history error: {0}")]
+    HistoryStoreError(#[from] kv::Error),
+    #[error(transparent)]
+    HistoryError(#[from] HistoryError),
+    #[error("Refn history of local repository is not available")]
+    HistoryUnavailable,
+    #[error("Found breaking changes in {0} OCA bundles. Use --allow-breaking to accept them")]
+    BreakingChanges(usize),
+    #[error("Found {0} lint errors")]
+    LintErrors(usize),
+    #[error("Unknown language: {0}. Use ISO 639-3 or 639-1 code, e.g. `eng`")]
+    UnknownLanguage(String),
+    #[error("Coverage of required languages is incomplete in {0} OCA bundles")]
+    IncompleteCoverage(usize),
+    #[error("Formatting {0} would change its OCA bundle")]
+    FormatChangesBundle(PathBuf),
+    #[error("{0} ocafiles are not formatted. Run `oca fmt` to format them")]
+    UnformattedFiles(usize),
+    #[error("{0} ocafiles could not be formatted")]
+    FormatFailed(usize),
+    #[error("Language server error: {0}")]
+    LspError(std::io::Error),
+    #[error("Invalid record in {0} line {1}: {2}")]
+    RecordsReadError(PathBuf, usize, String),
+    #[error("Found {0} invalid records")]
+    DataValidationFailed(usize),
+    #[error("Can't import JSON Schema {0}: {1}")]
+    JsonSchemaImportError(PathBuf, String),
+    #[error("Invalid entry in {0} line {1}: {2}")]
+    RepoImportError(PathBuf, usize, String),
+    #[error("Storage error: {0}")]
+    StorageError(String),
+    #[error("Found {0} problems in local repository")]
+    IntegrityError(usize),
+    #[error("Server error: {0}")]
+    ServeError(String),
     #[error("Unexpected error occurred: {0}")]
     Panic(String),
     #[error("Cache error: {0}")]
@@ -77,18 +130,23 @@ impl From<Vec<oca_rs::facade::build::Error>> for BuildingFailures {
 
 #[derive(Debug)]
 pub struct BuildingFailures(pub(crate) Vec<oca_rs::facade::build::Error>);
+
+impl BuildingFailures {
+    /// Returns all validation errors.
+    pub fn validation_errors(&self) -> impl Iterator<Item = &ValidationError> {
+        self.0.iter().flat_map(|e| match e {
+            oca_rs::facade::build::Error::ValidationError(valdation_errors) => valdation_errors,
+        })
+    }
+}
+
 impl std::fmt::Display for BuildingFailures {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let errs = self
-            .0
-            .iter()
-            .flat_map(|e| match e {
-                oca_rs::facade::build::Error::ValidationError(valdation_errors) => {
-                    valdation_errors.iter().map(|e| e.to_string())
-                }
-            })
+        let messages = self
+            .validation_errors()
+            .map(ToString::to_string)
             .collect::<Vec<_>>();
-        write!(f, "{}", errs.join("\n"))
+        write!(f, "{}", messages.join("\n"))
     }
 }
 
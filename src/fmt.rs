@@ -0,0 +1,216 @@
+use std::{fs, path::Path};
+
+use oca_rs::Facade;
+use serde_json::Value;
+
+use crate::{dependency_graph::MutableGraph, error::CliError};
+
+/// Splits command into whitespace separated tokens. Whitespace inside quotes
+/// and brackets doesn't split.
+fn split_tokens(line: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut depth = 0usize;
+    for c in line.chars() {
+        match quote {
+            Some(q) => {
+                current.push(c);
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '"' | '\'' => {
+                    quote = Some(c);
+                    current.push(c);
+                }
+                '[' | '{' => {
+                    depth += 1;
+                    current.push(c);
+                }
+                ']' | '}' => {
+                    depth = depth.saturating_sub(1);
+                    current.push(c);
+                }
+                c if c.is_whitespace() && depth == 0 => {
+                    if !current.is_empty() {
+                        tokens.push(std::mem::take(&mut current));
+                    }
+                }
+                c => current.push(c),
+            },
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    // Join `key = value` into `key=value`.
+    let mut joined: Vec<String> = vec![];
+    for token in tokens {
+        match joined.last_mut() {
+            Some(last) if token.starts_with('=') || last.ends_with('=') => last.push_str(&token),
+            _ => joined.push(token),
+        }
+    }
+    joined
+}
+
+/// Formats JSON value in one line, with space after `,` and `:`.
+fn format_json(value: &Value) -> String {
+    match value {
+        Value::Array(items) => format!(
+            "[{}]",
+            items.iter().map(format_json).collect::<Vec<_>>().join(", ")
+        ),
+        Value::Object(fields) => format!(
+            "{{{}}}",
+            fields
+                .iter()
+                .map(|(key, value)| format!(
+                    "{}: {}",
+                    Value::from(key.as_str()),
+                    format_json(value)
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        value => value.to_string(),
+    }
+}
+
+/// Returns value in canonical form: strings in double quotes and lists and
+/// objects with single spaces.
+fn format_value(value: &str) -> String {
+    if value.starts_with('[') || value.starts_with('{') {
+        return serde_json::from_str(value)
+            .map(|value| format_json(&value))
+            .unwrap_or_else(|_| value.to_string());
+    }
+    // Escape sequences are kept as written, so single quoted text with double
+    // quotes or escapes can't be changed.
+    match value
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+        .filter(|inner| !inner.contains(['"', '\\']))
+    {
+        Some(inner) => Value::from(inner).to_string(),
+        None => value.to_string(),
+    }
+}
+
+/// Returns key of `key=value` token.
+fn key(token: &str) -> Option<&str> {
+    let (key, _) = token.split_once('=')?;
+    (!key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-'))
+    .then_some(key)
+}
+
+fn format_command(line: &str, sort: bool) -> String {
+    let mut tokens = split_tokens(line)
+        .into_iter()
+        .map(|token| match key(&token) {
+            Some(key) => format!("{}={}", key, format_value(&token[key.len() + 1..])),
+            None => token,
+        })
+        .collect::<Vec<_>>();
+    if sort {
+        // Sort trailing `key=value` pairs by key.
+        let first_pair = tokens
+            .iter()
+            .rposition(|token| key(token).is_none())
+            .map_or(0, |i| i + 1);
+        tokens[first_pair..].sort_by(|a, b| key(a).cmp(&key(b)));
+    }
+    tokens.join(" ")
+}
+
+/// Returns ocafile in canonical layout. `#` comments and `--` meta lines,
+/// including the `-- name=` header, are kept in place as written, and runs of
+/// empty lines are collapsed. With `sort`, attributes of every command are
+/// ordered by name.
+pub fn format_ocafile(source: &str, sort: bool) -> String {
+    let mut lines: Vec<String> = vec![];
+    for line in source.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            if lines.last().is_some_and(|last| !last.is_empty()) {
+                lines.push(String::new());
+            }
+        } else if line.starts_with("--") || line.starts_with('#') {
+            lines.push(line.to_string());
+        } else {
+            lines.push(format_command(line, sort));
+        }
+    }
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+/// Formats ocafile and checks that formatted one describes the same OCA
+/// bundle. Attributes are sorted only if it doesn't change the bundle.
+/// Returns `None` if file is already formatted.
+pub fn format_file(
+    facade: &Facade,
+    graph: &mut MutableGraph,
+    path: &Path,
+) -> Result<Option<String>, CliError> {
+    let source =
+        fs::read_to_string(path).map_err(|e| CliError::ReadFileFailed(path.to_owned(), e))?;
+    let said = facade
+        .validate_ocafile_with_external_references(source.clone(), graph)
+        .map_err(|e| CliError::GrammarError(path.to_owned(), e))?
+        .oca_bundle
+        .said;
+    let mut said_of = |ocafile: String| {
+        facade
+            .validate_ocafile_with_external_references(ocafile, graph)
+            .ok()
+            .and_then(|build| build.oca_bundle.said)
+    };
+    let formatted = [true, false]
+        .into_iter()
+        .map(|sort| format_ocafile(&source, sort))
+        .find(|formatted| said.is_some() && said_of(formatted.clone()) == said)
+        .ok_or_else(|| CliError::FormatChangesBundle(path.to_owned()))?;
+    Ok((formatted != source).then_some(formatted))
+}
+
+#[test]
+fn test_format_ocafile() {
+    let source = "-- name=person\n\n\n--  comment   \nADD   ATTRIBUTE  name = Text age=Numeric  \nADD LABEL en ATTRS name='Name'  age=\"Age\"\nADD ENTRY_CODE ATTRS radio=[\"o1\",\"o2\"]\n\n";
+    assert_eq!(
+        format_ocafile(source, false),
+        "-- name=person\n\n--  comment\nADD ATTRIBUTE name=Text age=Numeric\nADD LABEL en ATTRS name=\"Name\" age=\"Age\"\nADD ENTRY_CODE ATTRS radio=[\"o1\", \"o2\"]\n"
+    );
+    assert_eq!(
+        format_command("ADD ATTRIBUTE name=Text age=Numeric", true),
+        "ADD ATTRIBUTE age=Numeric name=Text"
+    );
+    assert_eq!(
+        format_command("ADD LABEL en ATTRS name=\"Full name\"", false),
+        "ADD LABEL en ATTRS name=\"Full name\""
+    );
+    assert_eq!(
+        format_command("ADD LABEL en ATTRS name='First \"given\" name'", false),
+        "ADD LABEL en ATTRS name='First \"given\" name'"
+    );
+    assert_eq!(
+        format_ocafile("# note: x='y'  z\nADD ATTRIBUTE name=Text\n", false),
+        "# note: x='y'  z\nADD ATTRIBUTE name=Text\n"
+    );
+    let formatted = format_ocafile(source, true);
+    assert_eq!(format_ocafile(&formatted, true), formatted);
+}
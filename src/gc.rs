@@ -0,0 +1,215 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use oca_bundle_semantics::state::oca::OCABundle;
+use said::SelfAddressingIdentifier;
+
+use crate::{
+    config::{OCA_CACHE_DB_DIR, OCA_INDEX_DIR, OCA_REPOSITORY_DIR},
+    doctor::{build_named, bundle_dependencies, names_by_said, open_store},
+    error::CliError,
+    get_oca_facade,
+    history::RefnHistory,
+    publish::batches,
+    utils::resolve_said,
+};
+
+/// Directory inside local repository where kept bundles are rebuilt, before
+/// they replace the old storage.
+const GC_DIR: &str = "gc";
+/// Directory inside local repository where old storage is moved, until the
+/// rebuilt one is in place.
+const GC_OLD_DIR: &str = "gc_old";
+/// Storage directories replaced by garbage collection.
+const STORAGE_DIRS: [&str; 3] = [OCA_REPOSITORY_DIR, OCA_INDEX_DIR, OCA_CACHE_DB_DIR];
+
+#[derive(Debug, Default)]
+pub struct Garbage {
+    /// Direct dependencies of kept bundles, by SAID.
+    kept: HashMap<String, Vec<String>>,
+    /// Names of kept bundles, by SAID.
+    names: HashMap<String, Vec<String>>,
+    /// SAIDs of bundles to delete.
+    pub saids: Vec<String>,
+}
+
+impl Garbage {
+    pub fn kept(&self) -> usize {
+        self.kept.len()
+    }
+}
+
+/// Finds stored bundles that are not reachable from current names (refn),
+/// tagged SAIDs and SAIDs in `keep` list, directly or as dependencies.
+pub fn find_garbage(
+    local_repository_path: &Path,
+    history: Option<&RefnHistory>,
+    keep: &[String],
+) -> Result<Garbage, CliError> {
+    let (stored, facade) = open_store(local_repository_path)?;
+    let refs = facade.fetch_all_refs().unwrap_or_default();
+
+    let mut roots = refs.values().cloned().collect::<Vec<_>>();
+    if let Some(history) = history {
+        roots.extend(history.tagged_saids()?.iter().map(ToString::to_string));
+    }
+    for said_or_refn in keep {
+        roots.push(resolve_said(&facade, history, said_or_refn)?.to_string());
+    }
+
+    let mut kept = HashMap::new();
+    let mut visited = HashSet::new();
+    while let Some(said) = roots.pop() {
+        if !visited.insert(said.clone()) {
+            continue;
+        }
+        // Missing bundles are reported by `oca doctor`.
+        let json = match stored.get(&said) {
+            Some(json) => json,
+            None => continue,
+        };
+        let bundle: OCABundle = serde_json::from_slice(json).map_err(|e| {
+            CliError::StorageError(format!("OCA bundle {} is invalid: {}", said, e))
+        })?;
+        let dependencies = bundle_dependencies(&bundle, &refs)
+            .into_iter()
+            .filter(|dependency| stored.contains_key(dependency))
+            .collect::<Vec<_>>();
+        roots.extend(dependencies.iter().cloned());
+        kept.insert(said, dependencies);
+    }
+
+    let mut saids = stored
+        .keys()
+        .filter(|said| !kept.contains_key(*said))
+        .cloned()
+        .collect::<Vec<_>>();
+    saids.sort();
+    let names = names_by_said(&refs);
+    Ok(Garbage { kept, names, saids })
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else if path.exists() {
+        fs::remove_file(path)
+    } else {
+        Ok(())
+    }
+}
+
+/// Deletes garbage from storage and SQLite index. Kept bundles are rebuilt
+/// into new repository, in order of dependencies, which then replaces the old
+/// one. Old storage is moved aside and deleted only after the new one is in
+/// place.
+pub fn collect(local_repository_path: &Path, garbage: &Garbage) -> Result<(), CliError> {
+    let old_path = local_repository_path.join(GC_OLD_DIR);
+    if old_path.exists() {
+        return Err(CliError::StorageError(format!(
+            "Previous garbage collection was interrupted. Old storage is kept in {}",
+            old_path.display()
+        )));
+    }
+
+    let ocafiles = {
+        let facade = get_oca_facade(local_repository_path.to_path_buf());
+        batches(&garbage.kept)?
+            .into_iter()
+            .flatten()
+            .map(|said| {
+                let refns = garbage.names.get(&said).cloned().unwrap_or_default();
+                let said: SelfAddressingIdentifier = said.parse()?;
+                let ocafile = facade
+                    .get_oca_bundle_ocafile(said.clone(), true)
+                    .map_err(CliError::OcaBundleAstError)?;
+                Ok((said, ocafile, refns))
+            })
+            .collect::<Result<Vec<_>, CliError>>()?
+    };
+
+    let gc_path = local_repository_path.join(GC_DIR);
+    remove_path(&gc_path)?;
+    {
+        let mut facade = get_oca_facade(gc_path.clone());
+        for (said, ocafile, refns) in ocafiles {
+            build_named(&mut facade, &said, &ocafile, &refns)?;
+        }
+    }
+
+    fs::create_dir_all(&old_path)?;
+    for dir in STORAGE_DIRS {
+        let path = local_repository_path.join(dir);
+        if path.exists() {
+            fs::rename(path, old_path.join(dir))?;
+        }
+    }
+    if let Err(e) = swap_in(local_repository_path, &gc_path) {
+        // Put old storage back.
+        for dir in STORAGE_DIRS {
+            let path = local_repository_path.join(dir);
+            let kept_path = old_path.join(dir);
+            if kept_path.exists() {
+                remove_path(&path)?;
+                fs::rename(kept_path, path)?;
+            }
+        }
+        remove_path(&old_path)?;
+        return Err(e.into());
+    }
+    remove_path(&old_path)?;
+    remove_path(&gc_path)?;
+    Ok(())
+}
+
+/// Moves rebuilt storage directories into local repository.
+fn swap_in(local_repository_path: &Path, gc_path: &Path) -> std::io::Result<()> {
+    for dir in STORAGE_DIRS {
+        let new_path = gc_path.join(dir);
+        if new_path.exists() {
+            fs::rename(new_path, local_repository_path.join(dir))?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_gc() -> anyhow::Result<()> {
+    let tmp_dir = tempdir::TempDir::new("gc")?;
+    let path = tmp_dir.path();
+    {
+        let mut facade = get_oca_facade(path.to_path_buf());
+        for ocafile in [
+            "-- name=first\nADD ATTRIBUTE name=Text",
+            "-- name=first\nADD ATTRIBUTE name=Text age=Numeric",
+            "-- name=alias\nADD ATTRIBUTE name=Text age=Numeric",
+            "-- name=second\nADD ATTRIBUTE first=refn:first",
+            "ADD ATTRIBUTE unnamed=Text",
+        ] {
+            facade.build_from_ocafile(ocafile.to_string()).unwrap();
+        }
+    }
+
+    let garbage = find_garbage(path, None, &[]).unwrap();
+    assert_eq!(garbage.kept(), 2);
+    assert_eq!(garbage.saids.len(), 2);
+
+    collect(path, &garbage).unwrap();
+    let report = crate::doctor::check(path).unwrap();
+    assert_eq!(report.bundles, 2);
+    assert!(report.problems.is_empty(), "{:?}", report.problems);
+
+    let garbage = find_garbage(path, None, &[]).unwrap();
+    assert!(garbage.saids.is_empty());
+    let refs = get_oca_facade(path.to_path_buf())
+        .fetch_all_refs()
+        .unwrap_or_default();
+    assert_eq!(refs["first"], refs["alias"]);
+    assert!(!path.join(GC_DIR).exists());
+    assert!(!path.join(GC_OLD_DIR).exists());
+
+    Ok(())
+}
@@ -0,0 +1,299 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use kv::{Bucket, Config, Json, Store};
+use regex::Regex;
+use said::SelfAddressingIdentifier;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::cache::compute_hash;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub said: SelfAddressingIdentifier,
+    /// Time of the build. Missing in entries recorded by older versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Ocafile the bundle was built from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// Hash of ocafile contents, as used by build cache.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn new(said: SelfAddressingIdentifier, path: &Path, ocafile: &str) -> Self {
+        Self {
+            said,
+            timestamp: Some(Utc::now()),
+            path: Some(path.to_path_buf()),
+            hash: Some(compute_hash(ocafile.trim())),
+        }
+    }
+
+    /// Entry of SAID built before history was recorded.
+    pub fn untracked(said: SelfAddressingIdentifier) -> Self {
+        Self {
+            said,
+            timestamp: None,
+            path: None,
+            hash: None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error(transparent)]
+    Store(#[from] kv::Error),
+    #[error("Invalid version: {0}. Use semantic version, e.g. v1.2.0")]
+    InvalidVersion(String),
+    #[error("{refn} never pointed to SAID {said}")]
+    UnknownSaid {
+        refn: String,
+        said: SelfAddressingIdentifier,
+    },
+    #[error("Tag {refn}@{tag} already points to {said}. Use --force to move it")]
+    TagExists {
+        refn: String,
+        tag: String,
+        said: SelfAddressingIdentifier,
+    },
+    #[error("Unknown tag: {0}@{1}")]
+    UnknownTag(String, String),
+}
+
+/// Append-only list of SAIDs that each refn pointed to, oldest first, and
+/// version tags of those SAIDs.
+#[derive(Clone)]
+pub struct RefnHistory {
+    store: Store,
+}
+
+impl RefnHistory {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, kv::Error> {
+        let cfg = Config::new(path);
+        let store = Store::new(cfg)?;
+        Ok(Self { store })
+    }
+
+    fn bucket(&self) -> Result<Bucket<'_, String, Json<Vec<HistoryEntry>>>, kv::Error> {
+        self.store.bucket(Some("refn_history"))
+    }
+
+    fn tags_bucket(
+        &self,
+    ) -> Result<Bucket<'_, String, Json<BTreeMap<String, SelfAddressingIdentifier>>>, kv::Error>
+    {
+        self.store.bucket(Some("refn_tags"))
+    }
+
+    /// Appends entry to refn history, unless refn already points to its SAID.
+    pub fn record(&self, refn: &str, entry: HistoryEntry) -> Result<(), kv::Error> {
+        let bucket = self.bucket()?;
+        let mut entries = bucket
+            .get(&refn.to_string())?
+            .map(|el| el.0)
+            .unwrap_or_default();
+        if entries.last().map(|last| &last.said) == Some(&entry.said) {
+            return Ok(());
+        }
+        entries.push(entry);
+        bucket.set(&refn.to_string(), &Json(entries))?;
+        bucket.flush()?;
+        Ok(())
+    }
+
+    /// Records SAID built from ocafile. Several names can point to the same
+    /// SAID, so history is recorded under name of built ocafile.
+    pub fn record_build(
+        &self,
+        refn: &str,
+        said: &SelfAddressingIdentifier,
+        path: &Path,
+        ocafile: &str,
+    ) -> Result<(), kv::Error> {
+        self.record(refn, HistoryEntry::new(said.clone(), path, ocafile))
+    }
+
+    pub fn entries(&self, refn: &str) -> Result<Vec<HistoryEntry>, kv::Error> {
+        Ok(self
+            .bucket()?
+            .get(&refn.to_string())?
+            .map(|el| el.0)
+            .unwrap_or_default())
+    }
+
+    /// Returns version tags of refn.
+    pub fn tags(
+        &self,
+        refn: &str,
+    ) -> Result<BTreeMap<String, SelfAddressingIdentifier>, kv::Error> {
+        Ok(self
+            .tags_bucket()?
+            .get(&refn.to_string())?
+            .map(|el| el.0)
+            .unwrap_or_default())
+    }
+
+    /// Tags SAID that refn pointed to with semantic version. Existing tag is
+    /// moved only if `force` is set.
+    pub fn tag(
+        &self,
+        refn: &str,
+        version: &str,
+        said: &SelfAddressingIdentifier,
+        force: bool,
+    ) -> Result<(), HistoryError> {
+        let semver = Regex::new(r"^v?\d+\.\d+\.\d+(-[0-9A-Za-z.-]+)?(\+[0-9A-Za-z.-]+)?$").unwrap();
+        if !semver.is_match(version) {
+            return Err(HistoryError::InvalidVersion(version.to_string()));
+        }
+        if !self.entries(refn)?.iter().any(|entry| &entry.said == said) {
+            return Err(HistoryError::UnknownSaid {
+                refn: refn.to_string(),
+                said: said.clone(),
+            });
+        }
+        let mut tags = self.tags(refn)?;
+        match tags.get(version) {
+            Some(tagged) if tagged != said && !force => {
+                return Err(HistoryError::TagExists {
+                    refn: refn.to_string(),
+                    tag: version.to_string(),
+                    said: tagged.clone(),
+                })
+            }
+            _ => {}
+        }
+        tags.insert(version.to_string(), said.clone());
+        let bucket = self.tags_bucket()?;
+        bucket.set(&refn.to_string(), &Json(tags))?;
+        bucket.flush()?;
+        Ok(())
+    }
+
+    /// Returns SAID tagged with version.
+    pub fn tagged(
+        &self,
+        refn: &str,
+        version: &str,
+    ) -> Result<SelfAddressingIdentifier, HistoryError> {
+        self.tags(refn)?
+            .remove(version)
+            .ok_or(HistoryError::UnknownTag(
+                refn.to_string(),
+                version.to_string(),
+            ))
+    }
+
+    /// Returns SAIDs tagged with any version, of all refns.
+    pub fn tagged_saids(&self) -> Result<Vec<SelfAddressingIdentifier>, kv::Error> {
+        let mut saids = vec![];
+        for item in self.tags_bucket()?.iter() {
+            let tags: Json<BTreeMap<String, SelfAddressingIdentifier>> = item?.value()?;
+            saids.extend(tags.0.into_values());
+        }
+        Ok(saids)
+    }
+
+    /// Prints history of refn, newest first, with tags and the SAID it
+    /// points to now.
+    pub fn print(
+        &self,
+        refn: &str,
+        current: Option<&SelfAddressingIdentifier>,
+    ) -> Result<(), kv::Error> {
+        let mut entries = self.entries(refn)?;
+        // Refn could be built before history was recorded.
+        if let Some(current) = current {
+            if !entries.iter().any(|entry| &entry.said == current) {
+                entries.push(HistoryEntry::untracked(current.clone()));
+            }
+        }
+        let tags = self.tags(refn)?;
+        println!("History of {}:", refn);
+        for entry in entries.iter().rev() {
+            let mut labels = tags
+                .iter()
+                .filter(|(_, said)| **said == entry.said)
+                .map(|(tag, _)| tag.clone())
+                .collect::<Vec<_>>();
+            if Some(&entry.said) == current {
+                labels.insert(0, "current".to_string());
+            }
+            let labels = if labels.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", labels.join(", "))
+            };
+            println!("said {}{}", entry.said, labels);
+            if let Some(timestamp) = entry.timestamp {
+                println!(
+                    "  Date:   {}",
+                    timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+                );
+            }
+            if let Some(path) = &entry.path {
+                println!("  File:   {}", path.display());
+            }
+            if let Some(hash) = &entry.hash {
+                println!("  Hash:   {}", hash);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_refn_history() -> anyhow::Result<()> {
+    let tmp_dir = tempdir::TempDir::new("history")?;
+    let history = RefnHistory::new(tmp_dir.path())?;
+    let first: SelfAddressingIdentifier = "EKrgT8vjEMrFLp7JbrFIub2e3q3O1AL43uBeUellrXRz".parse()?;
+    let second: SelfAddressingIdentifier =
+        "EHp19U2U1sJgd3pEDXLTrNYHUUB0QRHbpGh-L8BCYOFL".parse()?;
+    let path = Path::new("person.ocafile");
+
+    history.record("person", HistoryEntry::new(first.clone(), path, "v1"))?;
+    // Rebuilding without changes doesn't add new entry.
+    history.record("person", HistoryEntry::new(first.clone(), path, "v1"))?;
+    history.record("person", HistoryEntry::new(second.clone(), path, "v2"))?;
+
+    let entries = history.entries("person")?;
+    let saids = entries
+        .iter()
+        .map(|entry| entry.said.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(saids, vec![first.clone(), second.clone()]);
+    assert_ne!(entries[0].hash, entries[1].hash);
+    assert_eq!(entries[1].path.as_deref(), Some(path));
+    assert!(history.entries("address")?.is_empty());
+
+    history.tag("person", "v1.0.0", &first, false)?;
+    assert_eq!(history.tagged("person", "v1.0.0")?, first);
+    assert!(matches!(
+        history.tag("person", "v1.0.0", &second, false),
+        Err(HistoryError::TagExists { .. })
+    ));
+    history.tag("person", "v1.0.0", &second, true)?;
+    assert_eq!(history.tagged("person", "v1.0.0")?, second);
+    assert!(matches!(
+        history.tag("person", "latest", &second, false),
+        Err(HistoryError::InvalidVersion(_))
+    ));
+    assert!(matches!(
+        history.tag("address", "v1.0.0", &second, false),
+        Err(HistoryError::UnknownSaid { .. })
+    ));
+    assert!(matches!(
+        history.tagged("person", "v2.0.0"),
+        Err(HistoryError::UnknownTag(..))
+    ));
+
+    Ok(())
+}
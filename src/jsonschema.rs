@@ -0,0 +1,731 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Subcommand;
+use oca_ast_semantics::ast::{NestedAttrType, RefValue};
+use oca_bundle_semantics::state::oca::OCABundle;
+use oca_rs::Facade;
+use said::SelfAddressingIdentifier;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    coverage::normalize_language,
+    data::typed_value,
+    diff::{overlay_fields, overlay_values},
+    error::CliError,
+    fmt::format_ocafile,
+    get_oca_facade,
+    list::attribute_type_name,
+    open_history,
+    utils::BundleSelector,
+};
+
+pub const JSON_SCHEMA_DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+#[derive(Subcommand)]
+pub enum ExportCommand {
+    /// Export oca object as JSON Schema (draft 2020-12)
+    Jsonschema {
+        #[command(flatten)]
+        bundle: BundleSelector,
+        /// Language of labels and information used as titles and
+        /// descriptions
+        #[arg(short, long, default_value = "eng")]
+        language: String,
+        /// Path to output file. Schema is printed if not given
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Returns entry codes as values of attribute type, e.g. numbers for
+/// `Numeric` attribute. Arrays take type of their items.
+fn enum_values(attr: &NestedAttrType, codes: &[Value]) -> Value {
+    let mut attr = attr;
+    while let NestedAttrType::Array(inner) = attr {
+        attr = inner;
+    }
+    let attr_type = attribute_type_name(attr);
+    codes
+        .iter()
+        .map(|code| match code {
+            Value::String(code) => typed_value(code, &attr_type),
+            code => code.clone(),
+        })
+        .collect()
+}
+
+struct Exporter<'a> {
+    facade: &'a Facade,
+    language: String,
+    /// Schemas of referenced bundles, by SAID.
+    defs: BTreeMap<String, Value>,
+}
+
+impl Exporter<'_> {
+    fn load(&self, said: SelfAddressingIdentifier) -> Result<OCABundle, CliError> {
+        Ok(self
+            .facade
+            .get_oca_bundle(said, false)
+            .map_err(CliError::OcaBundleAstError)?
+            .bundle)
+    }
+
+    /// Returns `$ref` to schema of referenced bundle, adding it to `$defs`.
+    fn reference(&mut self, said: SelfAddressingIdentifier) -> Result<Value, CliError> {
+        let key = said.to_string();
+        if !self.defs.contains_key(&key) {
+            // Placeholder stops recursion if bundle is referenced again
+            // while its schema is generated.
+            self.defs.insert(key.clone(), Value::Null);
+            let bundle = self.load(said)?;
+            let schema = self.object(&bundle)?;
+            self.defs.insert(key.clone(), schema);
+        }
+        Ok(json!({ "$ref": format!("#/$defs/{}", key) }))
+    }
+
+    fn attribute(&mut self, attr: &NestedAttrType) -> Result<Value, CliError> {
+        Ok(match attr {
+            NestedAttrType::Value(_) => match attribute_type_name(attr).as_str() {
+                "Numeric" => json!({"type": "number"}),
+                "Boolean" => json!({"type": "boolean"}),
+                "DateTime" => json!({"type": "string", "format": "date-time"}),
+                "Binary" => json!({"type": "string", "contentEncoding": "base64"}),
+                _ => json!({"type": "string"}),
+            },
+            NestedAttrType::Array(inner) => {
+                json!({"type": "array", "items": self.attribute(inner)?})
+            }
+            NestedAttrType::Reference(RefValue::Said(said)) => self.reference(said.clone())?,
+            NestedAttrType::Reference(RefValue::Name(refn)) => {
+                let said = self
+                    .facade
+                    .fetch_all_refs()
+                    .unwrap_or_default()
+                    .remove(refn)
+                    .ok_or_else(|| CliError::OCABundleRefnNotFound(refn.clone()))?;
+                self.reference(said.parse()?)?
+            }
+            NestedAttrType::Null => json!({"type": "null"}),
+        })
+    }
+
+    fn object(&mut self, bundle: &OCABundle) -> Result<Value, CliError> {
+        let overlays = overlay_fields(bundle);
+        let labels = overlay_values(&overlays, "label", Some(&self.language));
+        let information = overlay_values(&overlays, "information", Some(&self.language));
+        let entry_codes = overlay_values(&overlays, "entry_code", Some(&self.language));
+        let conformance = overlay_values(&overlays, "conformance", Some(&self.language));
+
+        let mut properties = Map::new();
+        let mut required = vec![];
+        for (name, attr) in &bundle.capture_base.attributes {
+            let mut property = self.attribute(attr)?;
+            if let Some(label) = labels.get(name) {
+                property["title"] = label.clone();
+            }
+            if let Some(description) = information.get(name) {
+                property["description"] = description.clone();
+            }
+            // Entry codes given as SAID of another object can't be listed.
+            if let Some(codes) = entry_codes.get(name).and_then(Value::as_array) {
+                let codes = enum_values(attr, codes);
+                match property.get_mut("items") {
+                    Some(items) => items["enum"] = codes,
+                    None => property["enum"] = codes,
+                }
+            }
+            if conformance.get(name).and_then(Value::as_str) == Some("M") {
+                required.push(name.clone());
+            }
+            properties.insert(name.clone(), property);
+        }
+
+        let mut schema = json!({
+            "type": "object",
+            "properties": properties,
+            "additionalProperties": false,
+        });
+        if !required.is_empty() {
+            schema["required"] = json!(required);
+        }
+        Ok(schema)
+    }
+}
+
+/// Returns JSON Schema of bundle. Referenced bundles are put into `$defs`,
+/// keyed by their SAIDs.
+pub fn to_json_schema(
+    facade: &Facade,
+    bundle: &OCABundle,
+    language: &str,
+) -> Result<Value, CliError> {
+    let language = normalize_language(language)
+        .ok_or_else(|| CliError::UnknownLanguage(language.to_string()))?;
+    let mut exporter = Exporter {
+        facade,
+        language,
+        defs: BTreeMap::new(),
+    };
+    let object = exporter.object(bundle)?;
+    let mut schema = json!({ "$schema": JSON_SCHEMA_DRAFT });
+    // Schemas in `$defs` have no `$id`, so `#/$defs/...` always points to
+    // root document.
+    if let Some(said) = &bundle.said {
+        schema["$id"] = json!(format!("urn:said:{}", said));
+    }
+    if let (Value::Object(schema), Value::Object(object)) = (&mut schema, object) {
+        schema.extend(object);
+    }
+    if !exporter.defs.is_empty() {
+        schema["$defs"] = json!(exporter.defs);
+    }
+    Ok(schema)
+}
+
+#[derive(Subcommand)]
+pub enum ImportCommand {
+    /// Import JSON Schema as ocafiles, one per object definition
+    Jsonschema {
+        /// Path to JSON Schema file
+        schema: PathBuf,
+        /// Directory for generated ocafiles
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Language of labels and information made from titles and
+        /// descriptions
+        #[arg(short, long, default_value = "en")]
+        language: String,
+    },
+}
+
+/// Returns name in snake_case, e.g. `postal_address` for `PostalAddress`.
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            if c.is_uppercase() && previous.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit())
+            {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else if !out.is_empty() && !out.ends_with('_') {
+            out.push('_');
+        }
+        previous = Some(c);
+    }
+    out.trim_end_matches('_').to_string()
+}
+
+fn is_object(schema: &Value) -> bool {
+    schema["type"] == "object" || schema.get("properties").is_some()
+}
+
+/// Returns ocafile string literal. Ocafile keeps escape sequences as
+/// written, so characters that need them are replaced: control characters by
+/// spaces, and backslashes are dropped. Text with double quotes is put in
+/// single quotes, and if it has both, double quotes become single ones.
+fn quote(value: &str) -> String {
+    let value = value
+        .chars()
+        .filter(|c| *c != '\\')
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>();
+    match (value.contains('"'), value.contains('\'')) {
+        (false, _) => format!("\"{}\"", value),
+        (true, false) => format!("'{}'", value),
+        (true, true) => format!("\"{}\"", value.replace('"', "'")),
+    }
+}
+
+/// Attribute of generated ocafile.
+struct Attribute {
+    attr_type: String,
+    label: Option<String>,
+    information: Option<String>,
+    entry_codes: Option<Vec<Value>>,
+}
+
+struct Importer<'a> {
+    root: &'a Value,
+    root_name: String,
+    /// Names of root schema and definitions, which inline objects can't take.
+    reserved: BTreeSet<String>,
+    language: String,
+    /// Generated ocafiles, by name.
+    ocafiles: BTreeMap<String, String>,
+}
+
+impl<'a> Importer<'a> {
+    /// Returns schema and name of definition pointed by `$ref`. Only
+    /// references within the same document are supported.
+    fn resolve(&self, reference: &str) -> Result<(&'a Value, String), String> {
+        let root: &'a Value = self.root;
+        if reference == "#" {
+            return Ok((root, self.root_name.clone()));
+        }
+        let pointer = reference
+            .strip_prefix('#')
+            .ok_or_else(|| format!("external reference `{}` is not supported", reference))?;
+        let schema = root
+            .pointer(pointer)
+            .ok_or_else(|| format!("reference `{}` not found", reference))?;
+        let name = pointer.rsplit('/').next().unwrap_or_default();
+        Ok((schema, snake_case(name)))
+    }
+
+    /// Returns `name`, or `name` with numeric suffix if it's already taken
+    /// by definition or another ocafile.
+    fn unique_name(&self, name: &str) -> String {
+        let taken =
+            |name: &String| self.reserved.contains(name) || self.ocafiles.contains_key(name);
+        let mut unique = name.to_string();
+        let mut n = 1;
+        while taken(&unique) {
+            n += 1;
+            unique = format!("{}_{}", name, n);
+        }
+        unique
+    }
+
+    /// Returns OCA type of property. Nested objects are generated as
+    /// separate ocafiles named `name`, or a unique name derived from it.
+    fn attribute_type(&mut self, name: &str, schema: &Value) -> Result<String, String> {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let (target, target_name) = self.resolve(reference)?;
+            return if is_object(target) {
+                if !self.ocafiles.contains_key(&target_name) {
+                    self.object(&target_name, target)?;
+                }
+                Ok(format!("refn:{}", target_name))
+            } else {
+                self.attribute_type(name, target)
+            };
+        }
+        // Nullable variants, e.g. `anyOf: [{"$ref": ...}, {"type": "null"}]`
+        // use the first type that is not null.
+        for key in ["anyOf", "oneOf", "allOf"] {
+            if let Some(variants) = schema.get(key).and_then(Value::as_array) {
+                if let Some(variant) = variants.iter().find(|v| v["type"] != "null") {
+                    return self.attribute_type(name, variant);
+                }
+            }
+        }
+        let json_type = match &schema["type"] {
+            Value::Array(types) => types.iter().find(|t| *t != "null").cloned(),
+            json_type => Some(json_type.clone()),
+        };
+        Ok(match json_type.as_ref().and_then(Value::as_str) {
+            _ if is_object(schema) => {
+                let name = self.unique_name(name);
+                self.object(&name, schema)?;
+                format!("refn:{}", name)
+            }
+            Some("array") => format!(
+                "Array[{}]",
+                self.attribute_type(name, schema.get("items").unwrap_or(&json!({})))?
+            ),
+            Some("number") | Some("integer") => "Numeric".to_string(),
+            Some("boolean") => "Boolean".to_string(),
+            Some("string") if schema.get("contentEncoding").is_some() => "Binary".to_string(),
+            Some("string")
+                if matches!(
+                    schema["format"].as_str(),
+                    Some("date-time") | Some("date") | Some("time")
+                ) =>
+            {
+                "DateTime".to_string()
+            }
+            _ => "Text".to_string(),
+        })
+    }
+
+    fn attribute(&mut self, name: &str, schema: &Value) -> Result<Attribute, String> {
+        let text = |key: &str| schema[key].as_str().map(str::to_string);
+        // Enums of array are given on its items.
+        let entry_codes = schema
+            .get("enum")
+            .or_else(|| schema["items"].get("enum"))
+            .and_then(Value::as_array)
+            .map(|codes| {
+                codes
+                    .iter()
+                    .filter(|code| !code.is_null())
+                    .map(|code| match code {
+                        Value::String(_) => code.clone(),
+                        code => Value::from(code.to_string()),
+                    })
+                    .collect()
+            });
+        Ok(Attribute {
+            attr_type: self.attribute_type(name, schema)?,
+            label: text("title"),
+            information: text("description"),
+            entry_codes,
+        })
+    }
+
+    /// Generates ocafile of object schema and ocafiles of all objects it
+    /// refers to.
+    fn object(&mut self, name: &str, schema: &Value) -> Result<(), String> {
+        // Reserve name, so recursive references don't generate it again.
+        self.ocafiles.insert(name.to_string(), String::new());
+        let empty = Map::new();
+        let properties = schema["properties"].as_object().unwrap_or(&empty);
+        if properties.is_empty() {
+            return Err(format!("object `{}` has no properties", name));
+        }
+        let mut attributes = vec![];
+        for (property, property_schema) in properties {
+            if property
+                .chars()
+                .any(|c| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            {
+                return Err(format!(
+                    "property `{}` isn't valid attribute name",
+                    property
+                ));
+            }
+            let nested_name = format!("{}_{}", name, snake_case(property));
+            attributes.push((property, self.attribute(&nested_name, property_schema)?));
+        }
+        let required = schema["required"]
+            .as_array()
+            .map(|required| {
+                required
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let mut lines = vec![format!("-- name={}", name)];
+        let command = |command: &str, pairs: Vec<String>| {
+            (!pairs.is_empty()).then(|| format!("{} ATTRS {}", command, pairs.join(" ")))
+        };
+        lines.push(format!(
+            "ADD ATTRIBUTE {}",
+            attributes
+                .iter()
+                .map(|(property, attr)| format!("{}={}", property, attr.attr_type))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ));
+        let labels = attributes
+            .iter()
+            .filter_map(|(property, attr)| {
+                let label = attr.label.as_ref()?;
+                Some(format!("{}={}", property, quote(label)))
+            })
+            .collect();
+        let information = attributes
+            .iter()
+            .filter_map(|(property, attr)| {
+                let information = attr.information.as_ref()?;
+                Some(format!("{}={}", property, quote(information)))
+            })
+            .collect();
+        let entry_codes = attributes
+            .iter()
+            .filter_map(|(property, attr)| {
+                let codes = attr
+                    .entry_codes
+                    .as_ref()
+                    .filter(|codes| !codes.is_empty())?;
+                Some(format!("{}={}", property, Value::from(codes.clone())))
+            })
+            .collect();
+        let conformance = attributes
+            .iter()
+            .filter(|(property, _)| required.contains(&property.as_str()))
+            .map(|(property, _)| format!("{}=\"M\"", property))
+            .collect();
+        lines.extend(
+            [
+                command(&format!("ADD LABEL {}", self.language), labels),
+                command(&format!("ADD INFORMATION {}", self.language), information),
+                command("ADD ENTRY_CODE", entry_codes),
+                command("ADD CONFORMANCE", conformance),
+            ]
+            .into_iter()
+            .flatten(),
+        );
+        self.ocafiles
+            .insert(name.to_string(), format_ocafile(&lines.join("\n"), false));
+        Ok(())
+    }
+}
+
+/// Returns ocafiles, by name, generated from JSON Schema. Root schema is
+/// named after its `title`, or `default_name` if it has none. Definitions
+/// are named after their keys.
+pub fn from_json_schema(
+    schema: &Value,
+    default_name: &str,
+    language: &str,
+) -> Result<BTreeMap<String, String>, String> {
+    let root_name = schema["title"]
+        .as_str()
+        .map(snake_case)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| snake_case(default_name));
+    let definitions = ["$defs", "definitions"]
+        .into_iter()
+        .flat_map(|key| schema[key].as_object().into_iter().flatten());
+    let mut importer = Importer {
+        root: schema,
+        root_name: root_name.clone(),
+        reserved: definitions
+            .map(|(name, _)| snake_case(name))
+            .chain([root_name.clone()])
+            .collect(),
+        language: language.to_string(),
+        ocafiles: BTreeMap::new(),
+    };
+    for key in ["$defs", "definitions"] {
+        for (name, definition) in schema[key].as_object().into_iter().flatten() {
+            let name = snake_case(name);
+            if is_object(definition) && !importer.ocafiles.contains_key(&name) {
+                importer.object(&name, definition)?;
+            }
+        }
+    }
+    if is_object(schema) {
+        importer.object(&root_name, schema)?;
+    }
+    if importer.ocafiles.is_empty() {
+        return Err("no object definitions found".to_string());
+    }
+    Ok(importer.ocafiles)
+}
+
+pub fn handle_import_command(command: &ImportCommand) -> Result<(), CliError> {
+    match command {
+        ImportCommand::Jsonschema {
+            schema,
+            output,
+            language,
+        } => {
+            normalize_language(language)
+                .ok_or_else(|| CliError::UnknownLanguage(language.to_string()))?;
+            let content = fs::read_to_string(schema)
+                .map_err(|e| CliError::ReadFileFailed(schema.clone(), e))?;
+            let import_error = |e: String| CliError::JsonSchemaImportError(schema.clone(), e);
+            let json: Value =
+                serde_json::from_str(&content).map_err(|e| import_error(e.to_string()))?;
+            let default_name = schema
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default()
+                .trim_end_matches(".schema");
+            let ocafiles = from_json_schema(&json, default_name, language).map_err(import_error)?;
+            fs::create_dir_all(output).map_err(CliError::WriteFileFailed)?;
+            for (name, ocafile) in &ocafiles {
+                let path = output.join(format!("{}.ocafile", name));
+                fs::write(&path, ocafile).map_err(CliError::WriteFileFailed)?;
+                println!("Created {}", path.display());
+            }
+            println!(
+                "Imported {} ocafiles. Build them with `oca build -d {}`",
+                ocafiles.len(),
+                output.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+pub fn handle_export_command(
+    command: &ExportCommand,
+    local_repository_path: &Path,
+) -> Result<(), CliError> {
+    match command {
+        ExportCommand::Jsonschema {
+            bundle,
+            language,
+            output,
+        } => {
+            let history = open_history(local_repository_path);
+            let facade = get_oca_facade(local_repository_path.to_path_buf());
+            let said = bundle.resolve(&facade, history.as_ref())?;
+            let oca_bundle = facade
+                .get_oca_bundle(said, false)
+                .map_err(CliError::OcaBundleAstError)?
+                .bundle;
+            let schema = to_json_schema(&facade, &oca_bundle, language)?;
+            let content = serde_json::to_string_pretty(&schema).map_err(CliError::WriteOcaError)?;
+            match output {
+                Some(output) => {
+                    fs::write(output, content).map_err(CliError::WriteFileFailed)?;
+                    println!("Exported JSON Schema to {}", output.display());
+                }
+                None => println!("{}", content),
+            }
+            Ok(())
+        }
+    }
+}
+
+#[test]
+fn test_json_schema() -> anyhow::Result<()> {
+    let tmp_dir = tempdir::TempDir::new("jsonschema")?;
+    let mut facade = get_oca_facade(tmp_dir.path().to_path_buf());
+    facade
+        .build_from_ocafile("-- name=address\nADD ATTRIBUTE street=Text".to_string())
+        .unwrap();
+    let ocafile = r#"-- name=person
+ADD ATTRIBUTE name=Text age=Numeric addresses=Array[refn:address] level=Text
+ADD ATTRIBUTE rating=Numeric
+ADD LABEL en ATTRS name="Name" age="Age"
+ADD LABEL pl ATTRS name="Imię"
+ADD INFORMATION en ATTRS name="Full name"
+ADD CONFORMANCE ATTRS name="M"
+ADD ENTRY_CODE ATTRS level=["low", "high"] rating=["1", "2"]
+"#;
+    let bundle = facade
+        .validate_ocafile(ocafile.to_string())
+        .unwrap()
+        .oca_bundle;
+
+    let schema = to_json_schema(&facade, &bundle, "en").unwrap();
+    assert_eq!(schema["$schema"], JSON_SCHEMA_DRAFT);
+    let properties = &schema["properties"];
+    assert_eq!(properties["name"]["title"], "Name");
+    assert_eq!(properties["name"]["description"], "Full name");
+    assert_eq!(properties["age"]["type"], "number");
+    assert_eq!(properties["level"]["enum"], json!(["high", "low"]));
+    assert_eq!(properties["rating"]["enum"], json!([1, 2]));
+    assert_eq!(schema["required"], json!(["name"]));
+
+    let reference = properties["addresses"]["items"]["$ref"].as_str().unwrap();
+    let said = reference.strip_prefix("#/$defs/").unwrap();
+    assert_eq!(
+        schema["$defs"][said]["properties"]["street"]["type"],
+        "string"
+    );
+
+    let schema = to_json_schema(&facade, &bundle, "pol").unwrap();
+    assert_eq!(schema["properties"]["name"]["title"], "Imię");
+    assert!(schema["properties"]["age"].get("title").is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_import_json_schema() -> anyhow::Result<()> {
+    let schema = json!({
+        "$schema": JSON_SCHEMA_DRAFT,
+        "title": "Person",
+        "type": "object",
+        "properties": {
+            "name": {"type": "string", "title": "Name", "description": "Full name"},
+            "born": {"type": "string", "format": "date"},
+            "level": {"type": "string", "enum": ["low", "high"]},
+            "home": {"$ref": "#/$defs/PostalAddress"},
+            "phones": {
+                "type": "array",
+                "items": {"type": "object", "properties": {"number": {"type": "string"}}}
+            },
+        },
+        "required": ["name"],
+        "$defs": {
+            "PostalAddress": {
+                "type": "object",
+                "properties": {"street": {"type": "string"}, "zip": {"type": ["integer", "null"]}}
+            }
+        }
+    });
+    let ocafiles = from_json_schema(&schema, "schema", "en").unwrap();
+    assert_eq!(
+        ocafiles.keys().collect::<Vec<_>>(),
+        vec!["person", "person_phones", "postal_address"]
+    );
+    assert_eq!(
+        ocafiles["person"],
+        r#"-- name=person
+ADD ATTRIBUTE name=Text born=DateTime level=Text home=refn:postal_address phones=Array[refn:person_phones]
+ADD LABEL en ATTRS name="Name"
+ADD INFORMATION en ATTRS name="Full name"
+ADD ENTRY_CODE ATTRS level=["low", "high"]
+ADD CONFORMANCE ATTRS name="M"
+"#
+    );
+    assert_eq!(
+        ocafiles["postal_address"],
+        "-- name=postal_address\nADD ATTRIBUTE street=Text zip=Numeric\n"
+    );
+    assert_eq!(snake_case("PostalAddress"), "postal_address");
+    assert_eq!(snake_case("postal-address v2"), "postal_address_v2");
+
+    let tmp_dir = tempdir::TempDir::new("jsonschema_import")?;
+    let mut facade = get_oca_facade(tmp_dir.path().to_path_buf());
+    for name in ["postal_address", "person_phones", "person"] {
+        facade.build_from_ocafile(ocafiles[name].clone()).unwrap();
+    }
+
+    // Literals are stored as written, without unescaping.
+    let schema = json!({
+        "title": "Person",
+        "type": "object",
+        "properties": {
+            "name": {
+                "type": "string",
+                "title": "First \"given\" name",
+                "description": "It's \"full\"\nname\\",
+            },
+        },
+    });
+    let ocafiles = from_json_schema(&schema, "schema", "en").unwrap();
+    let oca_rs::facade::bundle::BundleElement::Mechanics(built) = facade
+        .build_from_ocafile(ocafiles["person"].clone())
+        .unwrap()
+    else {
+        panic!("expected OCA bundle");
+    };
+    let stored = facade
+        .get_oca_bundle(built.said.unwrap(), false)
+        .unwrap()
+        .bundle;
+    let overlays = overlay_fields(&stored);
+    assert_eq!(
+        overlay_values(&overlays, "label", None)["name"],
+        "First \"given\" name"
+    );
+    assert_eq!(
+        overlay_values(&overlays, "information", None)["name"],
+        "It's 'full' name"
+    );
+
+    // Inline object doesn't replace definition of the same name.
+    let schema = json!({
+        "title": "Person",
+        "type": "object",
+        "properties": {
+            "phones": {"type": "object", "properties": {"number": {"type": "string"}}},
+            "work": {"$ref": "#/$defs/PersonPhones"},
+        },
+        "$defs": {
+            "PersonPhones": {"type": "object", "properties": {"office": {"type": "string"}}}
+        }
+    });
+    let ocafiles = from_json_schema(&schema, "schema", "en").unwrap();
+    assert_eq!(
+        ocafiles["person"],
+        "-- name=person\nADD ATTRIBUTE phones=refn:person_phones_2 work=refn:person_phones\n"
+    );
+    assert_eq!(
+        ocafiles["person_phones"],
+        "-- name=person_phones\nADD ATTRIBUTE office=Text\n"
+    );
+    assert_eq!(
+        ocafiles["person_phones_2"],
+        "-- name=person_phones_2\nADD ATTRIBUTE number=Text\n"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,442 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Display,
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+use lazy_static::lazy_static;
+use oca_bundle_semantics::state::oca::OCABundle;
+use oca_rs::Facade;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    dependency_graph::{parse_name, MutableGraph},
+    diff::{overlay_attributes, overlay_fields, OverlayKey},
+    error::CliError,
+    list::attribute_type_name,
+};
+
+lazy_static! {
+    static ref SNAKE_CASE: Regex = Regex::new(r"^[a-z][a-z0-9]*(_[a-z0-9]+)*$").unwrap();
+    static ref DISABLE: Regex = Regex::new(r"^#\s*oca-lint:\s*disable=(.+)$").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Off,
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Off => write!(f, "off"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Severity::Off),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!(
+                "unknown severity: {}. Use off, warning or error",
+                s
+            )),
+        }
+    }
+}
+
+/// Ocafile with OCA bundle built from it.
+pub struct LintContext<'a> {
+    pub source: &'a str,
+    pub refn: Option<&'a str>,
+    pub bundle: &'a OCABundle,
+    overlays: BTreeMap<OverlayKey, serde_json::Map<String, Value>>,
+}
+
+impl<'a> LintContext<'a> {
+    pub fn new(source: &'a str, refn: Option<&'a str>, bundle: &'a OCABundle) -> Self {
+        Self {
+            source,
+            refn,
+            bundle,
+            overlays: overlay_fields(bundle),
+        }
+    }
+
+    /// Languages of all overlays.
+    fn languages(&self) -> BTreeSet<&str> {
+        self.overlays
+            .keys()
+            .filter_map(|(_, language)| language.as_deref())
+            .collect()
+    }
+
+    fn has_overlay(&self, overlay_type: &str) -> bool {
+        self.overlays.keys().any(|(t, _)| t == overlay_type)
+    }
+
+    /// Returns attributes that overlay of given type and language has values
+    /// for.
+    fn overlay_attributes(&self, overlay_type: &str, language: Option<&str>) -> HashSet<&str> {
+        self.overlays
+            .iter()
+            .filter(|((t, l), _)| t == overlay_type && l.as_deref() == language)
+            .flat_map(|(_, fields)| overlay_attributes(fields))
+            .collect()
+    }
+
+    /// Returns number of line that adds the attribute.
+    fn attribute_line(&self, attribute: &str) -> Option<usize> {
+        let definition = format!("{}=", attribute);
+        self.source
+            .lines()
+            .position(|line| {
+                line.trim_start().starts_with("ADD ATTRIBUTE")
+                    && line
+                        .split_whitespace()
+                        .any(|part| part.starts_with(&definition))
+            })
+            .map(|i| i + 1)
+    }
+}
+
+/// Problem found by a rule, at given line of ocafile.
+type RuleIssue = (Option<usize>, String);
+
+pub struct Rule {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub default_severity: Severity,
+    check: fn(&LintContext) -> Vec<RuleIssue>,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        id: "label-translations",
+        description: "Every attribute has a label in every language used in ocafile",
+        default_severity: Severity::Warning,
+        check: label_translations,
+    },
+    Rule {
+        id: "information-overlay",
+        description: "Ocafile has information overlay",
+        default_severity: Severity::Warning,
+        check: information_overlay,
+    },
+    Rule {
+        id: "snake-case-refn",
+        description: "Name (refn) is snake_case",
+        default_severity: Severity::Warning,
+        check: snake_case_refn,
+    },
+    Rule {
+        id: "case-duplicate-attribute",
+        description: "No attribute names differ only by case",
+        default_severity: Severity::Error,
+        check: case_duplicate_attribute,
+    },
+    Rule {
+        id: "entry-translations",
+        description: "Attributes with entry codes have entries in every language",
+        default_severity: Severity::Warning,
+        check: entry_translations,
+    },
+    Rule {
+        id: "numeric-format-or-unit",
+        description: "Numeric attributes have format or unit",
+        default_severity: Severity::Warning,
+        check: numeric_format_or_unit,
+    },
+];
+
+pub fn rule(id: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|rule| rule.id == id)
+}
+
+fn label_translations(ctx: &LintContext) -> Vec<RuleIssue> {
+    let languages = ctx.languages();
+    if !ctx.has_overlay("label") {
+        return vec![(None, "OCA bundle has no labels".to_string())];
+    }
+    let mut issues = vec![];
+    for language in languages {
+        let labelled = ctx.overlay_attributes("label", Some(language));
+        for attribute in ctx.bundle.capture_base.attributes.keys() {
+            if !labelled.contains(attribute.as_str()) {
+                issues.push((
+                    ctx.attribute_line(attribute),
+                    format!("Attribute `{}` has no label in `{}`", attribute, language),
+                ));
+            }
+        }
+    }
+    issues
+}
+
+fn information_overlay(ctx: &LintContext) -> Vec<RuleIssue> {
+    if ctx.has_overlay("information") {
+        vec![]
+    } else {
+        vec![(None, "OCA bundle has no information overlay".to_string())]
+    }
+}
+
+fn snake_case_refn(ctx: &LintContext) -> Vec<RuleIssue> {
+    match ctx.refn {
+        Some(refn) if !SNAKE_CASE.is_match(refn) => {
+            let line = ctx
+                .source
+                .lines()
+                .position(|line| line.starts_with("--") && line.contains("name="))
+                .map(|i| i + 1);
+            vec![(line, format!("Name `{}` is not snake_case", refn))]
+        }
+        _ => vec![],
+    }
+}
+
+fn case_duplicate_attribute(ctx: &LintContext) -> Vec<RuleIssue> {
+    // Attributes are ordered by line, so the later one is reported.
+    let mut attributes = ctx
+        .bundle
+        .capture_base
+        .attributes
+        .keys()
+        .map(|attribute| (ctx.attribute_line(attribute), attribute.as_str()))
+        .collect::<Vec<_>>();
+    attributes.sort();
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    let mut issues = vec![];
+    for (line, attribute) in attributes {
+        if let Some(first) = seen.insert(attribute.to_lowercase(), attribute) {
+            issues.push((
+                line,
+                format!(
+                    "Attribute `{}` differs from `{}` only by case",
+                    attribute, first
+                ),
+            ));
+        }
+    }
+    issues
+}
+
+fn entry_translations(ctx: &LintContext) -> Vec<RuleIssue> {
+    let coded = ctx.overlay_attributes("entry_code", None);
+    let mut coded = coded.into_iter().collect::<Vec<_>>();
+    coded.sort();
+    let mut issues = vec![];
+    for language in ctx.languages() {
+        let entries = ctx.overlay_attributes("entry", Some(language));
+        for attribute in &coded {
+            if !entries.contains(attribute) {
+                issues.push((
+                    ctx.attribute_line(attribute),
+                    format!(
+                        "Attribute `{}` has entry codes, but no entries in `{}`",
+                        attribute, language
+                    ),
+                ));
+            }
+        }
+    }
+    issues
+}
+
+fn numeric_format_or_unit(ctx: &LintContext) -> Vec<RuleIssue> {
+    let described = ctx
+        .overlays
+        .keys()
+        .filter(|(t, _)| t == "format" || t == "unit")
+        .flat_map(|(t, l)| ctx.overlay_attributes(t, l.as_deref()))
+        .collect::<HashSet<_>>();
+    ctx.bundle
+        .capture_base
+        .attributes
+        .iter()
+        .filter(|(attribute, attr)| {
+            attribute_type_name(attr) == "Numeric" && !described.contains(attribute.as_str())
+        })
+        .map(|(attribute, _)| {
+            (
+                ctx.attribute_line(attribute),
+                format!("Numeric attribute `{}` has no format or unit", attribute),
+            )
+        })
+        .collect()
+}
+
+/// Rules disabled with `# oca-lint: disable=rule` comments. Comment placed
+/// before the first command disables rules in the whole file, otherwise only
+/// in the next command. `--` lines are reserved for ocafile metadata, so the
+/// directive is a regular `#` comment that other commands ignore.
+#[derive(Debug, Default)]
+struct Disabled {
+    file: HashSet<String>,
+    lines: HashMap<usize, HashSet<String>>,
+}
+
+impl Disabled {
+    fn parse(source: &str) -> Self {
+        let mut disabled = Disabled::default();
+        let mut pending: Option<HashSet<String>> = None;
+        let mut in_header = true;
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if let Some(captures) = DISABLE.captures(line) {
+                let rules = captures[1]
+                    .split(',')
+                    .map(|rule| rule.trim().to_string())
+                    .filter(|rule| !rule.is_empty());
+                if in_header {
+                    disabled.file.extend(rules);
+                } else {
+                    pending.get_or_insert_with(HashSet::new).extend(rules);
+                }
+            } else if !line.is_empty() && !line.starts_with("--") && !line.starts_with('#') {
+                in_header = false;
+                if let Some(rules) = pending.take() {
+                    disabled.lines.insert(i + 1, rules);
+                }
+            }
+        }
+        disabled
+    }
+
+    fn is_disabled(&self, rule: &str, line: Option<usize>) -> bool {
+        self.file.contains(rule)
+            || line
+                .and_then(|line| self.lines.get(&line))
+                .is_some_and(|rules| rules.contains(rule))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LintIssue {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl LintIssue {
+    pub fn format(&self, path: &Path) -> String {
+        let position = match self.line {
+            Some(line) => format!("{}:{}", path.display(), line),
+            None => path.display().to_string(),
+        };
+        format!(
+            "{}: {}[{}]: {}",
+            position, self.severity, self.rule, self.message
+        )
+    }
+}
+
+/// Runs all rules that are not turned off in `severities` or disabled in
+/// ocafile.
+pub fn lint(ctx: &LintContext, severities: &BTreeMap<String, Severity>) -> Vec<LintIssue> {
+    let disabled = Disabled::parse(ctx.source);
+    RULES
+        .iter()
+        .filter_map(|rule| {
+            let severity = severities
+                .get(rule.id)
+                .copied()
+                .unwrap_or(rule.default_severity);
+            (severity != Severity::Off).then_some((rule, severity))
+        })
+        .flat_map(|(rule, severity)| {
+            (rule.check)(ctx)
+                .into_iter()
+                .filter(|(line, _)| !disabled.is_disabled(rule.id, *line))
+                .map(move |(line, message)| LintIssue {
+                    rule: rule.id,
+                    severity,
+                    line,
+                    message,
+                })
+        })
+        .collect()
+}
+
+/// Validates ocafile against ocafiles in graph and lints OCA bundle built
+/// from it.
+pub fn lint_file(
+    facade: &Facade,
+    graph: &mut MutableGraph,
+    path: &Path,
+    severities: &BTreeMap<String, Severity>,
+) -> Result<Vec<LintIssue>, CliError> {
+    let source =
+        fs::read_to_string(path).map_err(|e| CliError::ReadFileFailed(path.to_owned(), e))?;
+    let (refn, _) = parse_name(path).map_err(|e| CliError::GraphError(e.into()))?;
+    let bundle = facade
+        .validate_ocafile_with_external_references(source.clone(), graph)
+        .map_err(|e| CliError::GrammarError(path.to_owned(), e))?
+        .oca_bundle;
+    let ctx = LintContext::new(&source, refn.as_deref(), &bundle);
+    Ok(lint(&ctx, severities))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{lint, LintContext, Severity};
+
+    fn rules(ocafile: &str, severities: &BTreeMap<String, Severity>) -> Vec<&'static str> {
+        let tmp_dir = tempdir::TempDir::new("lint").unwrap();
+        let mut facade = crate::get_oca_facade(tmp_dir.path().to_path_buf());
+        let bundle = facade
+            .validate_ocafile(ocafile.to_string())
+            .unwrap()
+            .oca_bundle;
+        let refn = ocafile
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("-- name="));
+        let ctx = LintContext::new(ocafile, refn, &bundle);
+        let mut rules = lint(&ctx, severities)
+            .into_iter()
+            .map(|issue| issue.rule)
+            .collect::<Vec<_>>();
+        rules.dedup();
+        rules
+    }
+
+    #[test]
+    fn test_rules() {
+        let ocafile = "-- name=PersonData\nADD ATTRIBUTE name=Text Name=Text age=Numeric\nADD LABEL en ATTRS name=\"Name\" Name=\"Name\" age=\"Age\"\nADD LABEL fr ATTRS name=\"Nom\"";
+        assert_eq!(
+            rules(ocafile, &BTreeMap::new()),
+            vec![
+                "label-translations",
+                "information-overlay",
+                "snake-case-refn",
+                "case-duplicate-attribute",
+                "numeric-format-or-unit"
+            ]
+        );
+
+        let severities = BTreeMap::from([
+            ("information-overlay".to_string(), Severity::Off),
+            ("numeric-format-or-unit".to_string(), Severity::Off),
+        ]);
+        let ocafile = "-- name=person\n# oca-lint: disable=label-translations\nADD ATTRIBUTE name=Text\n# oca-lint: disable=case-duplicate-attribute\nADD ATTRIBUTE Name=Text";
+        assert!(rules(ocafile, &severities).is_empty());
+    }
+}
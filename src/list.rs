@@ -0,0 +1,441 @@
+use isolang::Language;
+use oca_ast_semantics::ast::NestedAttrType;
+use oca_bundle_semantics::state::oca::OCABundle;
+use oca_rs::Facade;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{diff::short_overlay_type, doctor::names_by_said, error::CliError};
+
+#[derive(clap::Args, Debug, Default)]
+pub struct ListFilter {
+    /// Search oca objects in local index by phrase from their name or
+    /// description (meta overlay)
+    #[arg(long)]
+    pub query: Option<String>,
+    /// Show only oca objects with name (refn) matching glob pattern, e.g. `person*`
+    #[arg(long, conflicts_with = "name_regex")]
+    pub name: Option<String>,
+    /// Show only oca objects with name (refn) matching regular expression
+    #[arg(long)]
+    pub name_regex: Option<String>,
+    /// Show only oca objects with attribute of given name
+    #[arg(long)]
+    pub attribute: Option<String>,
+    /// Show only oca objects with attribute of given type, e.g. `Text`,
+    /// `Array[Numeric]` or `Reference`
+    #[arg(long)]
+    pub attribute_type: Option<String>,
+    /// Show only oca objects with overlay of given type, e.g. `label`
+    #[arg(long)]
+    pub overlay: Option<String>,
+    /// Show only oca objects with overlays in given language, e.g. `eng` or `en`
+    #[arg(long)]
+    pub language: Option<String>,
+    /// Show only oca objects of given classification
+    #[arg(long)]
+    pub classification: Option<String>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default)]
+pub enum ListFormat {
+    /// `SAID: <said>, name: <refn>` line for every oca object
+    #[default]
+    Plain,
+    Table,
+    Json,
+    Csv,
+}
+
+/// Single row of `oca list` output.
+#[derive(Serialize, Debug)]
+pub struct ListEntry {
+    pub said: String,
+    /// All names (refn) pointing to the bundle, sorted.
+    pub names: Vec<String>,
+    pub classification: String,
+    pub attributes: Vec<String>,
+    pub overlays: Vec<String>,
+}
+
+/// Returns name of attribute type, as used by `--attribute-type` filter.
+pub fn attribute_type_name(attr: &NestedAttrType) -> String {
+    match attr {
+        NestedAttrType::Value(attr_type) => serde_json::to_value(attr_type)
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default(),
+        NestedAttrType::Array(inner) => format!("Array[{}]", attribute_type_name(inner)),
+        NestedAttrType::Reference(_) => "Reference".to_string(),
+        NestedAttrType::Null => "Null".to_string(),
+    }
+}
+
+/// Returns overlays of bundle as `(type, language)` pairs.
+pub fn overlays(bundle: &OCABundle) -> Vec<(String, Option<String>)> {
+    bundle
+        .overlays
+        .iter()
+        .map(|overlay| {
+            let overlay_type = serde_json::to_value(overlay.overlay_type())
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_string))
+                .unwrap_or_default();
+            let language = overlay.language().map(|lang| lang.to_639_3().to_string());
+            (overlay_type, language)
+        })
+        .collect()
+}
+
+/// Converts glob pattern with `*` and `?` wildcards into regular expression.
+fn glob_to_regex(glob: &str) -> String {
+    let pattern = glob
+        .split('*')
+        .map(|part| {
+            part.split('?')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(".")
+        })
+        .collect::<Vec<_>>()
+        .join(".*");
+    format!("^{}$", pattern)
+}
+
+/// Compiled `ListFilter`.
+pub struct Matcher<'a> {
+    filter: &'a ListFilter,
+    name: Option<Regex>,
+}
+
+impl ListFilter {
+    pub fn matcher(&self) -> Result<Matcher<'_>, CliError> {
+        let name = match (&self.name, &self.name_regex) {
+            (Some(glob), _) => Some(Regex::new(&glob_to_regex(glob))?),
+            (None, Some(regex)) => Some(Regex::new(regex)?),
+            (None, None) => None,
+        };
+        Ok(Matcher { filter: self, name })
+    }
+}
+
+impl<'a> Matcher<'a> {
+    /// Checks bundle against the filter. Name filters match if any of
+    /// bundle names matches.
+    pub fn matches(&self, bundle: &OCABundle, names: &[String]) -> bool {
+        let filter = self.filter;
+        if let Some(regex) = &self.name {
+            if !names.iter().any(|name| regex.is_match(name)) {
+                return false;
+            }
+        }
+        let attributes = &bundle.capture_base.attributes;
+        if let Some(attribute) = &filter.attribute {
+            if !attributes.contains_key(attribute) {
+                return false;
+            }
+        }
+        if let Some(attribute_type) = &filter.attribute_type {
+            if !attributes
+                .values()
+                .any(|attr| attribute_type_name(attr).eq_ignore_ascii_case(attribute_type))
+            {
+                return false;
+            }
+        }
+        if let Some(classification) = &filter.classification {
+            if &bundle.capture_base.classification != classification {
+                return false;
+            }
+        }
+        if filter.overlay.is_some() || filter.language.is_some() {
+            let overlay_filter = filter.overlay.as_ref().map(|o| o.to_lowercase());
+            let language_filter = filter.language.as_ref().map(|l| l.to_lowercase());
+            let any_overlay = bundle.overlays.iter().any(|overlay| {
+                let type_matches = overlay_filter.as_ref().is_none_or(|expected| {
+                    serde_json::to_value(overlay.overlay_type())
+                        .ok()
+                        .and_then(|value| value.as_str().map(short_overlay_type))
+                        .is_some_and(|overlay_type| overlay_type.eq_ignore_ascii_case(expected))
+                });
+                let language_matches = language_filter.as_ref().is_none_or(|expected| {
+                    overlay.language().is_some_and(|lang| {
+                        lang.to_639_3() == expected || lang.to_639_1() == Some(expected.as_str())
+                    })
+                });
+                type_matches && language_matches
+            });
+            if !any_overlay {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Returns page of bundles from local index, and total number of bundles.
+/// With `query`, bundles are searched by index instead of listed.
+fn fetch_page(
+    facade: &Facade,
+    filter: &ListFilter,
+    page_size: usize,
+    page: usize,
+) -> Result<(Vec<OCABundle>, usize), CliError> {
+    match &filter.query {
+        Some(query) => {
+            let language = filter.language.as_deref().and_then(|language| {
+                Language::from_639_3(language).or_else(|| Language::from_639_1(language))
+            });
+            let result = facade.search_oca_bundle(language, query.clone(), page_size, page);
+            let bundles = result
+                .records
+                .into_iter()
+                .map(|record| record.oca_bundle)
+                .collect();
+            Ok((bundles, result.metadata.total))
+        }
+        None => {
+            let result = facade
+                .fetch_all_oca_bundle(page_size, page)
+                .map_err(CliError::OcaBundleAstError)?;
+            Ok((result.records, result.metadata.total))
+        }
+    }
+}
+
+/// Pages through local index and returns bundles matching the filter,
+/// skipping `offset` first matches. Filters other than `query` are checked
+/// on fetched bundles, because index doesn't store attributes and overlays.
+pub fn list_bundles(
+    facade: &Facade,
+    filter: &ListFilter,
+    limit: Option<usize>,
+    offset: usize,
+) -> Result<Vec<ListEntry>, CliError> {
+    let matcher = filter.matcher()?;
+    let names = names_by_said(&facade.fetch_all_refs().unwrap_or_default());
+
+    let page_size = 100;
+    let mut page = 1;
+    let mut seen = 0;
+    let mut skipped = 0;
+    let mut out = vec![];
+    loop {
+        let (bundles, total) = fetch_page(facade, filter, page_size, page)?;
+        let count = bundles.len();
+        seen += count;
+        for bundle in bundles {
+            let said = bundle
+                .said
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            let bundle_names = names.get(&said).cloned().unwrap_or_default();
+            if !matcher.matches(&bundle, &bundle_names) {
+                continue;
+            }
+            if skipped < offset {
+                skipped += 1;
+                continue;
+            }
+            if limit.is_some_and(|limit| out.len() >= limit) {
+                return Ok(out);
+            }
+            out.push(ListEntry {
+                said,
+                names: bundle_names,
+                classification: bundle.capture_base.classification.clone(),
+                attributes: bundle.capture_base.attributes.keys().cloned().collect(),
+                overlays: overlays(&bundle)
+                    .into_iter()
+                    .map(|(overlay_type, language)| match language {
+                        Some(language) => format!("{} ({})", overlay_type, language),
+                        None => overlay_type,
+                    })
+                    .collect(),
+            });
+        }
+        if count == 0 || seen >= total {
+            return Ok(out);
+        }
+        page += 1;
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn format_entries(entries: &[ListEntry], format: ListFormat) -> String {
+    match format {
+        ListFormat::Plain => entries
+            .iter()
+            .map(|entry| match entry.names.is_empty() {
+                false => format!("SAID: {}, name: {}", entry.said, entry.names.join(", ")),
+                true => format!("SAID: {}", entry.said),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ListFormat::Json => serde_json::to_string_pretty(entries).unwrap(),
+        ListFormat::Csv => {
+            let mut lines = vec!["said,names,classification,attributes,overlays".to_string()];
+            lines.extend(entries.iter().map(|entry| {
+                [
+                    entry.said.clone(),
+                    entry.names.join(" "),
+                    entry.classification.clone(),
+                    entry.attributes.join(" "),
+                    // Overlays have language after space, so `;` separates them.
+                    entry.overlays.join(";"),
+                ]
+                .iter()
+                .map(|field| csv_field(field))
+                .collect::<Vec<_>>()
+                .join(",")
+            }));
+            lines.join("\n")
+        }
+        ListFormat::Table => {
+            let name = |entry: &ListEntry| match entry.names.is_empty() {
+                false => entry.names.join(", "),
+                true => "-".to_string(),
+            };
+            let name_width = entries
+                .iter()
+                .map(|entry| name(entry).len())
+                .max()
+                .unwrap_or(0)
+                .max("NAME".len());
+            let mut lines = vec![format!(
+                "{:<44}  {:<name_width$}  ATTRS  OVERLAYS",
+                "SAID",
+                "NAME",
+                name_width = name_width
+            )];
+            lines.extend(entries.iter().map(|entry| {
+                format!(
+                    "{:<44}  {:<name_width$}  {:<5}  {}",
+                    entry.said,
+                    name(entry),
+                    entry.attributes.len(),
+                    entry.overlays.join(", "),
+                    name_width = name_width
+                )
+            }));
+            lines.join("\n")
+        }
+    }
+}
+
+#[test]
+fn test_glob_to_regex() -> anyhow::Result<()> {
+    let regex = Regex::new(&glob_to_regex("person_*.v?"))?;
+    assert!(regex.is_match("person_address.v1"));
+    assert!(regex.is_match("person_.v2"));
+    assert!(!regex.is_match("person_address.v10"));
+    assert!(!regex.is_match("my_person_address.v1"));
+    Ok(())
+}
+
+#[test]
+fn test_list_filters() -> anyhow::Result<()> {
+    let tmp_dir = tempdir::TempDir::new("list")?;
+    let mut facade = crate::get_oca_facade(tmp_dir.path().to_path_buf());
+    let ocafiles = [
+        "-- name=person\nADD ATTRIBUTE name=Text age=Numeric\nADD LABEL en ATTRS name=\"Name\" age=\"Age\"",
+        "-- name=address\nADD ATTRIBUTE street=Text numbers=Array[Numeric]\nADD ENTRY_CODE ATTRS street=[\"main\", \"side\"]",
+        "-- name=person_address\nADD ATTRIBUTE person=refn:person address=refn:address",
+        // Alias of `person`.
+        "-- name=human\nADD ATTRIBUTE name=Text age=Numeric\nADD LABEL en ATTRS name=\"Name\" age=\"Age\"",
+    ];
+    for ocafile in ocafiles {
+        facade.build_from_ocafile(ocafile.to_string()).unwrap();
+    }
+
+    let names = |filter: ListFilter| -> Vec<String> {
+        let mut names = list_bundles(&facade, &filter, None, 0)
+            .unwrap()
+            .into_iter()
+            .flat_map(|entry| entry.names)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+
+    let by_name = ListFilter {
+        name: Some("person*".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(names(by_name), vec!["human", "person", "person_address"]);
+    let by_alias = ListFilter {
+        name: Some("hum*".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(names(by_alias), vec!["human", "person"]);
+
+    let by_attribute = ListFilter {
+        attribute: Some("street".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(names(by_attribute), vec!["address"]);
+
+    let by_type = ListFilter {
+        attribute_type: Some("array[numeric]".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(names(by_type), vec!["address"]);
+
+    let by_language = ListFilter {
+        overlay: Some("label".to_string()),
+        language: Some("en".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(names(by_language), vec!["human", "person"]);
+
+    let by_overlay = ListFilter {
+        overlay: Some("entry".to_string()),
+        ..Default::default()
+    };
+    assert!(names(by_overlay).is_empty());
+    let by_overlay = ListFilter {
+        overlay: Some("entry_code".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(names(by_overlay), vec!["address"]);
+
+    let all = list_bundles(&facade, &ListFilter::default(), None, 0).unwrap();
+    assert_eq!(all.len(), 3);
+    let paged = list_bundles(&facade, &ListFilter::default(), Some(1), 1).unwrap();
+    assert_eq!(paged.len(), 1);
+    assert_eq!(paged[0].said, all[1].said);
+
+    let plain = format_entries(&paged, ListFormat::Plain);
+    assert_eq!(
+        plain,
+        format!(
+            "SAID: {}, name: {}",
+            paged[0].said,
+            paged[0].names.join(", ")
+        )
+    );
+
+    let by_alias = ListFilter {
+        name: Some("hum*".to_string()),
+        ..Default::default()
+    };
+    let person = list_bundles(&facade, &by_alias, None, 0).unwrap();
+    let csv = format_entries(&person, ListFormat::Csv);
+    assert_eq!(
+        csv.lines().nth(1).unwrap(),
+        format!(
+            "{},human person,,age name,spec/overlays/label/1.0 (eng)",
+            person[0].said
+        )
+    );
+
+    Ok(())
+}
@@ -0,0 +1,622 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use lazy_static::lazy_static;
+use oca_bundle_semantics::state::oca::OCABundle;
+use oca_rs::{facade::build::ValidationError, Facade};
+use regex::Regex;
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::{
+    dependency_graph::{MutableGraph, Node},
+    diagnostic::Diagnostic,
+    error::CliError,
+    list::attribute_type_name,
+    utils::visit_dirs_recursive,
+};
+
+lazy_static! {
+    static ref REFN: Regex = Regex::new(r"refn:([^\s\]]+)").unwrap();
+}
+
+/// Attribute types offered in completion of `ADD ATTRIBUTE`.
+const ATTRIBUTE_TYPES: &[&str] = &[
+    "Text",
+    "Numeric",
+    "Boolean",
+    "Binary",
+    "DateTime",
+    "Array[Text]",
+    "refn:",
+];
+
+/// JSON-RPC error codes.
+const INVALID_PARAMS: i64 = -32602;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Position in document, as line and character, starting from 0. Characters
+/// are counted as Unicode scalar values, which is what clients send for
+/// ASCII ocafiles.
+type Position = (usize, usize);
+
+/// Reads single message framed with `Content-Length` header. Returns `None`
+/// when input is closed.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+    let length = length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+fn range(start: Position, end: Position) -> Value {
+    json!({
+        "start": {"line": start.0, "character": start.1},
+        "end": {"line": end.0, "character": end.1},
+    })
+}
+
+fn uri(path: &Path) -> String {
+    Url::from_file_path(path)
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| path.display().to_string())
+}
+
+fn document_path(params: &Value) -> Option<PathBuf> {
+    let uri = params["textDocument"]["uri"].as_str()?;
+    Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+fn position(params: &Value) -> Option<Position> {
+    let position = &params["position"];
+    Some((
+        position["line"].as_u64()? as usize,
+        position["character"].as_u64()? as usize,
+    ))
+}
+
+/// Returns name (refn) at position, with its range. Names are recognized in
+/// `refn:<name>` references and in `-- name=<name>` line.
+fn refn_at(text: &str, (line, character): Position) -> Option<(String, Position, Position)> {
+    let content = text.lines().nth(line)?;
+    let chars = content.chars().collect::<Vec<_>>();
+    let is_word = |c: &char| c.is_alphanumeric() || matches!(c, '_' | '-' | ':');
+    let start = chars[..character.min(chars.len())]
+        .iter()
+        .rposition(|c| !is_word(c))
+        .map_or(0, |i| i + 1);
+    let end = chars[start..]
+        .iter()
+        .position(|c| !is_word(c))
+        .map_or(chars.len(), |i| start + i);
+    let word = chars[start..end].iter().collect::<String>();
+    if let Some(refn) = word.strip_prefix("refn:") {
+        let start = start + "refn:".len();
+        return (!refn.is_empty()).then(|| (refn.to_string(), (line, start), (line, end)));
+    }
+    let prefix = chars[..start].iter().collect::<String>();
+    (line == 0 && prefix.starts_with("--") && prefix.ends_with("name=") && !word.is_empty())
+        .then_some((word, (line, start), (line, end)))
+}
+
+/// Returns edits that rename `old` refn to `new` in ocafile.
+fn rename_edits(text: &str, old: &str, new: &str) -> Vec<Value> {
+    let mut edits = vec![];
+    for (i, line) in text.lines().enumerate() {
+        if i == 0 && line.starts_with("--") {
+            if let Some(name) = line.split("name=").nth(1) {
+                if name.trim() == old {
+                    let start = line.chars().count() - name.chars().count();
+                    edits.push(json!({
+                        "range": range((i, start), (i, start + old.chars().count())),
+                        "newText": new,
+                    }));
+                }
+            }
+        }
+        for captures in REFN.captures_iter(line) {
+            let name = captures.get(1).unwrap();
+            if name.as_str() == old {
+                let start = line[..name.start()].chars().count();
+                edits.push(json!({
+                    "range": range((i, start), (i, start + old.chars().count())),
+                    "newText": new,
+                }));
+            }
+        }
+    }
+    edits
+}
+
+fn markdown_attributes(refn: &str, bundle: &OCABundle) -> String {
+    let mut lines = vec![format!("**{}**", refn)];
+    if let Some(said) = &bundle.said {
+        lines.push(format!("`{}`", said));
+    }
+    lines.push(String::new());
+    for (name, attr) in &bundle.capture_base.attributes {
+        lines.push(format!("- `{}`: {}", name, attribute_type_name(attr)));
+    }
+    lines.join("\n")
+}
+
+/// Language server for ocafiles of a directory, talking LSP over stdio.
+pub struct LanguageServer {
+    root: PathBuf,
+    facade: Facade,
+    graph: MutableGraph,
+    /// Text of open documents, that may differ from files on disk.
+    documents: HashMap<PathBuf, String>,
+}
+
+impl LanguageServer {
+    pub fn new(root: PathBuf, facade: Facade) -> Result<Self, CliError> {
+        let graph = MutableGraph::new(visit_dirs_recursive(&root)?)?;
+        Ok(Self {
+            root,
+            facade,
+            graph,
+            documents: HashMap::new(),
+        })
+    }
+
+    /// Handles messages until client sends `exit` or closes input.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        while let Some(message) = read_message(&mut input)? {
+            let method = message["method"].as_str().unwrap_or_default().to_string();
+            info!("LSP message: {}", method);
+            if method == "exit" {
+                break;
+            }
+            let params = &message["params"];
+            match message.get("id") {
+                Some(id) => {
+                    let response = match self.request(&method, params) {
+                        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                        Err((code, message)) => json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "error": {"code": code, "message": message},
+                        }),
+                    };
+                    write_message(&mut output, &response)?;
+                }
+                None => {
+                    for notification in self.notification(&method, params) {
+                        write_message(&mut output, &notification)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        let invalid = || (INVALID_PARAMS, format!("Invalid params of {}", method));
+        match method {
+            "initialize" => {
+                if let Some(root) = params["rootUri"]
+                    .as_str()
+                    .and_then(|uri| Url::parse(uri).ok()?.to_file_path().ok())
+                {
+                    self.root = root;
+                    self.reload_graph();
+                }
+                Ok(json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "definitionProvider": true,
+                        "hoverProvider": true,
+                        "renameProvider": true,
+                        "completionProvider": {"triggerCharacters": [":", "="]},
+                    },
+                    "serverInfo": {"name": "oca", "version": env!("CARGO_PKG_VERSION")},
+                }))
+            }
+            "shutdown" => Ok(Value::Null),
+            "textDocument/definition" => {
+                let path = document_path(params).ok_or_else(invalid)?;
+                let position = position(params).ok_or_else(invalid)?;
+                Ok(self.definition(&path, position).unwrap_or(Value::Null))
+            }
+            "textDocument/hover" => {
+                let path = document_path(params).ok_or_else(invalid)?;
+                let position = position(params).ok_or_else(invalid)?;
+                Ok(self.hover(&path, position).unwrap_or(Value::Null))
+            }
+            "textDocument/completion" => {
+                let path = document_path(params).ok_or_else(invalid)?;
+                let position = position(params).ok_or_else(invalid)?;
+                Ok(Value::Array(self.completion(&path, position)))
+            }
+            "textDocument/rename" => {
+                let path = document_path(params).ok_or_else(invalid)?;
+                let position = position(params).ok_or_else(invalid)?;
+                let new_name = params["newName"].as_str().ok_or_else(invalid)?;
+                self.rename(&path, position, new_name)
+                    .map_err(|e| (INVALID_PARAMS, e))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+        }
+    }
+
+    /// Handles notification and returns notifications to send back.
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let path = match document_path(params) {
+            Some(path) => path,
+            None => return vec![],
+        };
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(path.clone(), text.to_string());
+            }
+            "textDocument/didChange" => {
+                // Server asks for full sync, so the last change is whole text.
+                if let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    self.documents.insert(path.clone(), text.to_string());
+                }
+            }
+            "textDocument/didSave" => self.reload_graph(),
+            "textDocument/didClose" => {
+                self.documents.remove(&path);
+                return vec![];
+            }
+            _ => return vec![],
+        }
+        vec![self.diagnostics(&path)]
+    }
+
+    fn reload_graph(&mut self) {
+        match visit_dirs_recursive(&self.root)
+            .and_then(|paths| MutableGraph::new(paths).map_err(CliError::from))
+        {
+            Ok(graph) => self.graph = graph,
+            Err(e) => warn!("Can't load ocafiles of {}: {}", self.root.display(), e),
+        }
+    }
+
+    fn text(&self, path: &Path) -> Option<String> {
+        self.documents
+            .get(path)
+            .cloned()
+            .or_else(|| fs::read_to_string(path).ok())
+    }
+
+    /// Adds refn of document to the graph, as validation saves SAID under
+    /// it. Document may be not saved yet, or its `-- name=` may differ from
+    /// the one on disk.
+    fn register(&mut self, path: &Path, text: &str) {
+        let Some(refn) = text
+            .lines()
+            .next()
+            .and_then(|line| line.split("name=").nth(1))
+            .map(|name| name.trim().trim_matches('"').to_string())
+            .filter(|name| !name.is_empty())
+        else {
+            return;
+        };
+        if self.graph.node(&refn).is_ok() {
+            return;
+        }
+        let renamed = self
+            .graph
+            .sort()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|node| node.path == path);
+        let result = match renamed {
+            Some(node) => self.graph.update_refn(&node.refn, refn),
+            None => {
+                let dependencies = REFN
+                    .captures_iter(text)
+                    .map(|captures| captures[1].to_string())
+                    .filter(|dependency| self.graph.node(dependency).is_ok())
+                    .collect::<BTreeSet<_>>();
+                let node = Node {
+                    refn,
+                    path: path.to_path_buf(),
+                    said: None,
+                };
+                self.graph
+                    .insert_node(node, dependencies.into_iter().collect())
+            }
+        };
+        if let Err(e) = result {
+            warn!("Can't add {} to graph: {}", path.display(), e);
+        }
+    }
+
+    /// Validates ocafile, after ocafiles it references, so their SAIDs are
+    /// known to the graph.
+    fn validate(&mut self, path: &Path, text: &str) -> Result<OCABundle, Vec<ValidationError>> {
+        self.register(path, text);
+        let mut dependencies = vec![];
+        for refn in REFN
+            .captures_iter(text)
+            .map(|captures| captures[1].to_string())
+        {
+            if let Ok(nodes) = self.graph.get_descendants(&refn) {
+                dependencies.extend(nodes);
+            }
+            dependencies.extend(self.graph.node(&refn));
+        }
+        let mut validated = BTreeSet::new();
+        for node in dependencies {
+            if !validated.insert(node.refn.clone()) {
+                continue;
+            }
+            if let Some(dependency) = self.text(&node.path) {
+                let _ = self
+                    .facade
+                    .validate_ocafile_with_external_references(dependency, &mut self.graph);
+            }
+        }
+        self.facade
+            .validate_ocafile_with_external_references(text.to_string(), &mut self.graph)
+            .map(|build| build.oca_bundle)
+    }
+
+    fn diagnostics(&mut self, path: &Path) -> Value {
+        let text = self.text(path).unwrap_or_default();
+        let errors = self.validate(path, &text).err().unwrap_or_default();
+        let diagnostics = errors
+            .iter()
+            .map(|error| {
+                let diagnostic = Diagnostic::new(path, Some(&text), error);
+                let (line, column) = diagnostic.location.unwrap_or((1, 1));
+                let start = (line.saturating_sub(1), column.saturating_sub(1));
+                let mut message = diagnostic.message.clone();
+                if let Some(hint) = &diagnostic.hint {
+                    message = format!("{}\nhint: {}", message, hint);
+                }
+                json!({
+                    "range": range(start, (start.0, start.1 + diagnostic.span)),
+                    "severity": 1,
+                    "source": "oca",
+                    "message": message,
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": {"uri": uri(path), "diagnostics": diagnostics},
+        })
+    }
+
+    fn definition(&self, path: &Path, position: Position) -> Option<Value> {
+        let (refn, _, _) = refn_at(&self.text(path)?, position)?;
+        let target = self.graph.oca_file_path(&refn).ok()?;
+        Some(json!({"uri": uri(&target), "range": range((0, 0), (0, 0))}))
+    }
+
+    fn hover(&mut self, path: &Path, position: Position) -> Option<Value> {
+        let (refn, start, end) = refn_at(&self.text(path)?, position)?;
+        let from_file = self
+            .graph
+            .oca_file_path(&refn)
+            .ok()
+            .and_then(|target| Some((self.text(&target)?, target)))
+            .and_then(|(text, target)| self.validate(&target, &text).ok());
+        let bundle = match from_file {
+            Some(bundle) => bundle,
+            // Referenced bundle may be only in local repository.
+            None => {
+                let said = self.facade.fetch_all_refs().ok()?.remove(&refn)?;
+                self.facade
+                    .get_oca_bundle(said.parse().ok()?, false)
+                    .ok()?
+                    .bundle
+            }
+        };
+        Some(json!({
+            "contents": {"kind": "markdown", "value": markdown_attributes(&refn, &bundle)},
+            "range": range(start, end),
+        }))
+    }
+
+    fn completion(&self, path: &Path, (line, character): Position) -> Vec<Value> {
+        let text = self.text(path).unwrap_or_default();
+        let prefix = text
+            .lines()
+            .nth(line)
+            .map(|line| line.chars().take(character).collect::<String>())
+            .unwrap_or_default();
+        let token = prefix.split_whitespace().last().unwrap_or_default();
+        if !prefix.ends_with(char::is_whitespace) && token.contains("refn:") {
+            let mut refns = self
+                .graph
+                .sort()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|node| node.refn)
+                .collect::<BTreeSet<_>>();
+            refns.extend(self.facade.fetch_all_refs().unwrap_or_default().into_keys());
+            return refns
+                .into_iter()
+                .map(|refn| json!({"label": refn, "kind": 18}))
+                .collect();
+        }
+        let is_attribute = prefix
+            .split_whitespace()
+            .take(2)
+            .map(str::to_uppercase)
+            .eq(["ADD", "ATTRIBUTE"]);
+        if is_attribute && token.ends_with('=') {
+            return ATTRIBUTE_TYPES
+                .iter()
+                .map(|attribute_type| json!({"label": attribute_type, "kind": 25}))
+                .collect();
+        }
+        vec![]
+    }
+
+    fn rename(&self, path: &Path, position: Position, new_name: &str) -> Result<Value, String> {
+        let text = self.text(path).unwrap_or_default();
+        let (old, _, _) = refn_at(&text, position).ok_or("No name (refn) at position")?;
+        if new_name.is_empty()
+            || new_name
+                .chars()
+                .any(|c| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        {
+            return Err(format!("Invalid name (refn): {}", new_name));
+        }
+        let mut paths = visit_dirs_recursive(&self.root)
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+        paths.extend(self.documents.keys().cloned());
+        let changes = paths
+            .iter()
+            .filter_map(|path| {
+                let edits = rename_edits(&self.text(path)?, &old, new_name);
+                (!edits.is_empty()).then(|| (uri(path), Value::Array(edits)))
+            })
+            .collect::<BTreeMap<_, _>>();
+        Ok(json!({ "changes": changes }))
+    }
+}
+
+#[test]
+fn test_refn_at() {
+    let text = "-- name=person\nADD ATTRIBUTE address=refn:address\n";
+    assert_eq!(
+        refn_at(text, (1, 30)),
+        Some(("address".to_string(), (1, 27), (1, 34)))
+    );
+    assert_eq!(
+        refn_at(text, (0, 10)),
+        Some(("person".to_string(), (0, 8), (0, 14)))
+    );
+    assert_eq!(refn_at(text, (1, 2)), None);
+
+    let edits = rename_edits(
+        "-- name=address\nADD ATTRIBUTE home=refn:address work=refn:address_work\n",
+        "address",
+        "location",
+    );
+    assert_eq!(edits.len(), 2);
+    assert_eq!(edits[1]["range"]["start"]["character"], 24);
+}
+
+#[test]
+fn test_language_server() -> anyhow::Result<()> {
+    let tmp_dir = tempdir::TempDir::new("lsp")?;
+    let address = tmp_dir.path().join("address.ocafile");
+    fs::write(&address, "-- name=address\nADD ATTRIBUTE street=Text\n")?;
+    let person = tmp_dir.path().join("person.ocafile");
+    let person_text = "-- name=person\nADD ATTRIBUTE home=refn:address age=Numric\n";
+    fs::write(&person, person_text)?;
+
+    let messages = [
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+        json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {"uri": uri(&person), "text": person_text},
+        }}),
+        json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/definition", "params": {
+            "textDocument": {"uri": uri(&person)},
+            "position": {"line": 1, "character": 27},
+        }}),
+        json!({"jsonrpc": "2.0", "method": "exit"}),
+    ];
+    let mut input = vec![];
+    for message in &messages {
+        write_message(&mut input, message)?;
+    }
+    let mut output = vec![];
+    let facade = crate::get_oca_facade(tmp_dir.path().join("repository"));
+    let mut server = LanguageServer::new(tmp_dir.path().to_path_buf(), facade).unwrap();
+    server.run(io::Cursor::new(input), &mut output)?;
+
+    let mut reader = io::Cursor::new(output);
+    let initialized = read_message(&mut reader)?.unwrap();
+    assert_eq!(
+        initialized["result"]["capabilities"]["renameProvider"],
+        true
+    );
+    let diagnostics = read_message(&mut reader)?.unwrap();
+    assert_eq!(diagnostics["method"], "textDocument/publishDiagnostics");
+    assert!(!diagnostics["params"]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+    let definition = read_message(&mut reader)?.unwrap();
+    assert_eq!(definition["result"]["uri"], uri(&address));
+
+    Ok(())
+}
+
+#[test]
+fn test_document_not_on_disk() -> anyhow::Result<()> {
+    let tmp_dir = tempdir::TempDir::new("lsp")?;
+    let address = tmp_dir.path().join("address.ocafile");
+    fs::write(&address, "-- name=address\nADD ATTRIBUTE street=Text\n")?;
+    let person = tmp_dir.path().join("person.ocafile");
+    fs::write(&person, "-- name=person\nADD ATTRIBUTE name=Text\n")?;
+    let draft = tmp_dir.path().join("draft.ocafile");
+
+    let messages = [
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+        // Unsaved file.
+        json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {"uri": uri(&draft), "text": "-- name=draft\nADD ATTRIBUTE home=refn:address\n"},
+        }}),
+        // Name changed in editor only.
+        json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {"uri": uri(&person), "text": "-- name=human\nADD ATTRIBUTE name=Text\n"},
+        }}),
+        json!({"jsonrpc": "2.0", "method": "exit"}),
+    ];
+    let mut input = vec![];
+    for message in &messages {
+        write_message(&mut input, message)?;
+    }
+    let mut output = vec![];
+    let facade = crate::get_oca_facade(tmp_dir.path().join("repository"));
+    let mut server = LanguageServer::new(tmp_dir.path().to_path_buf(), facade).unwrap();
+    server.run(io::Cursor::new(input), &mut output)?;
+
+    let mut reader = io::Cursor::new(output);
+    read_message(&mut reader)?.unwrap();
+    for path in [&draft, &person] {
+        let diagnostics = read_message(&mut reader)?.unwrap();
+        assert_eq!(diagnostics["params"]["uri"], uri(path));
+        assert_eq!(diagnostics["params"]["diagnostics"], json!([]));
+    }
+    assert_eq!(server.graph.oca_file_path("draft")?, draft);
+    assert_eq!(server.graph.oca_file_path("human")?, person);
+
+    Ok(())
+}
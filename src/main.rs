@@ -3,24 +3,50 @@ use build::handle_publish;
 use build::rebuild;
 use config::create_or_open_local_storage;
 use config::OCA_CACHE_DB_DIR;
+use config::OCA_HISTORY_DIR;
 use config::OCA_INDEX_DIR;
 use config::OCA_REPOSITORY_DIR;
+use config_command::{handle_config_command, ConfigCommand};
+use data::{handle_data_command, DataCommand};
 use dependency_graph::parse_name;
 use dependency_graph::GraphError;
+use diagnostic::render;
+use diff::{diff, DiffFormat};
+use error::BuildingFailures;
 use error::CliError;
+use gc::{collect, find_garbage};
+use history::{HistoryEntry, RefnHistory};
+use jsonschema::{handle_export_command, handle_import_command, ExportCommand, ImportCommand};
+use list::{format_entries, list_bundles, ListFilter, ListFormat};
 use oca_presentation::presentation::Presentation;
+use pack::Pack;
 use presentation_command::PresentationCommand;
+use publish::{publish_batches, publish_in_order};
+use repo::{handle_repo_command, RepoCommand};
+use report::{format_report, ValidateFormat};
+use serve::RepositoryServer;
 use std::collections::HashSet;
 use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::{env, fs, fs::File, io::Write, path::PathBuf, process, str::FromStr};
+use std::{
+    env, fs,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+};
 use tui::app::App;
+use utils::get_from_repo;
 use utils::handle_panic;
 use utils::load_nodes;
 use utils::load_remote_repo_url;
+use utils::referenced_saids;
 use utils::send_to_repo;
 use utils::visit_dirs_recursive;
+use utils::DEFAULT_TIMEOUT;
+use utils::{resolve_refn, resolve_said, BundleSelector};
 
 use clap::Parser as ClapParser;
 use clap::Subcommand;
@@ -31,7 +57,7 @@ use crate::config::{init_or_read_config, write_config, Config, OCA_DIR_NAME};
 use crate::dependency_graph::parse_node;
 use crate::dependency_graph::DependencyGraph;
 use crate::dependency_graph::MutableGraph;
-use crate::presentation_command::{handle_generate, handle_validate, Format};
+use crate::presentation_command::{handle_generate, handle_validate, Format, PresentationError};
 use crate::tui::logging::initialize_logging;
 use crate::utils::{load_ocafiles_all, visit_current_dir};
 use said::SelfAddressingIdentifier;
@@ -44,11 +70,31 @@ extern crate log;
 
 mod build;
 mod cache;
+mod compat;
 mod config;
+mod config_command;
+mod coverage;
+mod data;
 mod dependency_graph;
+mod diagnostic;
+mod diff;
+mod doctor;
 pub mod error;
+mod fmt;
+mod gc;
+mod history;
+mod jsonschema;
+mod lint;
+mod list;
+mod lsp;
 mod mapping;
+mod pack;
 pub mod presentation_command;
+mod publish;
+mod repo;
+mod report;
+mod sample;
+mod serve;
 mod tui;
 mod utils;
 mod validate;
@@ -58,8 +104,12 @@ mod validate;
 struct Args {
     #[command(subcommand)]
     command: Option<Commands>,
-    #[arg(short, long)]
-    config_path: Option<String>,
+    /// Path to config file. Overrides `OCA_CONFIG` variable
+    #[arg(short, long, global = true)]
+    config_path: Option<PathBuf>,
+    /// Never ask for input, fail instead. Also enabled by `CI` variable
+    #[arg(long, global = true)]
+    non_interactive: bool,
 }
 
 #[derive(Subcommand)]
@@ -67,8 +117,11 @@ struct Args {
 enum Commands {
     /// Initialize new local repository
     Init {},
-    /// Show configuration where data are stored
-    Config {},
+    /// Show configuration where data are stored, or edit config
+    Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommand>,
+    },
     /// Build oca objects out of ocafile
     #[clap(group = clap::ArgGroup::new("build").multiple(true).required(true).args(&["ocafile", "directory"]))]
     Build {
@@ -85,6 +138,16 @@ enum Commands {
         diff: bool,
         #[arg(short, long, requires = "publish")]
         repository_url: Option<String>,
+        /// Name of remote repository from config file to publish to
+        #[arg(long, requires = "publish", conflicts_with = "repository_url")]
+        remote: Option<String>,
+        /// Classify changes of rebuilt names (refn) as major, minor or patch
+        /// and fail on breaking ones, before anything is published
+        #[arg(long)]
+        check_compat: bool,
+        /// Don't fail on breaking changes found by `--check-compat`
+        #[arg(long, requires = "check_compat")]
+        allow_breaking: bool,
     },
     /// Validate oca objects out of ocafile
     #[clap(group = clap::ArgGroup::new("build").multiple(true).required(true).args(&["ocafile", "directory"]))]
@@ -95,46 +158,228 @@ enum Commands {
         /// Validate oca objects from directory (recursive)
         #[arg(short, long, group = "build")]
         directory: Option<PathBuf>,
+        /// Output format. `sarif` and `junit` are meant for code review and
+        /// CI tools
+        #[arg(long, value_enum, default_value_t)]
+        format: ValidateFormat,
+    },
+    /// Check ocafiles against style and quality rules. Severity of rules is
+    /// set in `[lint]` table of config file, and rules can be disabled in
+    /// ocafile with `# oca-lint: disable=<rule>` comment
+    #[clap(group = clap::ArgGroup::new("lint").multiple(true).required(true).args(&["ocafile", "directory", "list_rules"]))]
+    Lint {
+        /// Specify ocafile to lint
+        #[arg(short = 'f', long, group = "lint")]
+        ocafile: Option<Vec<PathBuf>>,
+        /// Lint ocafiles from directory (recursive)
+        #[arg(short, long, group = "lint")]
+        directory: Option<PathBuf>,
+        /// List available rules with their severity
+        #[arg(long, group = "lint")]
+        list_rules: bool,
+    },
+    /// Show which attributes miss labels, information or entries in which
+    /// language
+    Coverage {
+        /// Check ocafiles from directory (recursive)
+        #[arg(short, long)]
+        directory: PathBuf,
+        /// Fail if coverage of given languages is incomplete, e.g.
+        /// `--require eng,pol`
+        #[arg(long, value_delimiter = ',')]
+        require: Vec<String>,
+    },
+    /// Rewrite ocafiles in canonical layout, keeping comments and the
+    /// `-- name=` line
+    Fmt {
+        /// Specify ocafile to format
+        #[arg(short = 'f', long, conflicts_with = "directory")]
+        ocafile: Option<Vec<PathBuf>>,
+        /// Format ocafiles from directory (recursive). Default is current
+        /// directory
+        #[arg(short, long)]
+        directory: Option<PathBuf>,
+        /// Don't write files, only fail if any of them is not formatted
+        #[arg(long)]
+        check: bool,
     },
     /// Publish oca objects into online repository
-    #[clap(group = clap::ArgGroup::new("publish").required(true).args(&["said", "directory"]))]
+    #[clap(group = clap::ArgGroup::new("publish").required(true).args(&["said", "directory", "from_pack"]))]
     Publish {
         #[arg(short, long)]
         repository_url: Option<String>,
+        /// Name of remote repository from config file to publish to
+        #[arg(long, conflicts_with = "repository_url")]
+        remote: Option<String>,
+        /// SAID of OCA bundle to publish. Name (refn) or `name@version` is
+        /// accepted as well
         #[arg(short, long, group = "publish")]
         said: Option<String>,
         #[arg(long, action, requires = "directory")]
         diff: bool,
         #[arg(short, long, group = "publish")]
         directory: Option<PathBuf>,
+        /// Publish OCA bundles from pack file created by `export` command
+        #[arg(long, group = "publish")]
+        from_pack: Option<PathBuf>,
         #[arg(short, long)]
         timeout: Option<u64>,
         #[arg(short, long)]
         all: bool,
+        /// Number of OCA bundles published concurrently
+        #[arg(short, long, default_value_t = 4)]
+        jobs: usize,
     },
-    /// Show ocafile for specify said
-    Show {
+    /// Fetch oca object and its dependencies from online repository into
+    /// local repository
+    Fetch {
         #[arg(short, long)]
         said: String,
         #[arg(short, long)]
+        repository_url: Option<String>,
+        /// Name of remote repository from config file to fetch from
+        #[arg(long, conflicts_with = "repository_url")]
+        remote: Option<String>,
+        #[arg(short, long)]
+        timeout: Option<u64>,
+    },
+    /// Show ocafile for specify said or name (refn)
+    Show {
+        #[command(flatten)]
+        bundle: BundleSelector,
+        #[arg(short, long)]
         ast: bool,
         #[arg(short, long)]
         dereference: bool,
+        /// List every SAID that the name (refn) has pointed to
+        #[arg(long)]
+        history: bool,
     },
-    /// Get oca bundle for specify said
+    /// Get oca bundle for specify said or name (refn)
     Get {
-        #[arg(short, long)]
-        said: String,
+        #[command(flatten)]
+        bundle: BundleSelector,
         #[arg(short, long)]
         with_dependencies: bool,
+        /// List every SAID that the name (refn) has pointed to
+        #[arg(long)]
+        history: bool,
+    },
+    /// Show every SAID that name (refn) has pointed to
+    Log {
+        /// Name (refn) of OCA bundle
+        #[arg(short, long)]
+        refn: String,
+    },
+    /// Tag SAID of name (refn) with semantic version, e.g. `oca tag person v1.2.0`.
+    /// Tagged version can be selected with `person@v1.2.0`
+    Tag {
+        /// Name (refn) of OCA bundle
+        refn: String,
+        /// Semantic version, e.g. v1.2.0
+        version: String,
+        /// SAID to tag. Default is the one name points to now
+        #[arg(short, long)]
+        said: Option<String>,
+        /// Move tag if it already exists
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Show what changed between two oca objects, including objects they
+    /// reference
+    Diff {
+        /// SAID of old oca object. Name (refn) or `name@version` is accepted
+        /// as well
+        from: String,
+        /// SAID of new oca object. Name (refn) or `name@version` is accepted
+        /// as well
+        to: String,
+        #[arg(long, value_enum, default_value_t)]
+        format: DiffFormat,
     },
     /// List of all oca objects stored in local repository
-    List {},
+    List {
+        #[command(flatten)]
+        filter: ListFilter,
+        /// Output format
+        #[arg(long, value_enum, default_value_t)]
+        format: ListFormat,
+        /// Maximum number of oca objects to show
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Number of matching oca objects to skip
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+    },
+    /// Export oca object into pack file, that can be imported into another
+    /// local repository or published later, or into other format
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Export {
+        #[command(subcommand)]
+        command: Option<ExportCommand>,
+        #[arg(short, long, required = true)]
+        said: Option<String>,
+        /// Include all oca objects that the exported one depends on
+        #[arg(short, long)]
+        with_dependencies: bool,
+        /// Path to output pack file
+        #[arg(short, long, required = true)]
+        output: Option<PathBuf>,
+    },
+    /// Import oca objects from pack file into local repository, or generate
+    /// ocafiles from other format
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Import {
+        #[command(subcommand)]
+        command: Option<ImportCommand>,
+        /// Path to pack file
+        #[arg(required = true)]
+        pack: Option<PathBuf>,
+    },
+    /// Work with data records described by oca objects
+    Data {
+        #[command(subcommand)]
+        command: DataCommand,
+    },
+    /// Export or import whole local repository
+    Repo {
+        #[command(subcommand)]
+        command: RepoCommand,
+    },
+    /// Check integrity of local repository
+    #[command(alias = "fsck")]
+    Doctor {
+        /// Rebuild index of local repository from stored oca objects
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Delete oca objects that are not reachable from names (refn), tagged
+    /// SAIDs or kept SAIDs, including their dependencies
+    Gc {
+        /// Only list oca objects that would be deleted
+        #[arg(long)]
+        dry_run: bool,
+        /// SAID or name (refn) of oca object to keep
+        #[arg(long)]
+        keep: Vec<String>,
+    },
+    /// Run local mock of OCA repository, serving oca objects from local repository
+    Serve {
+        /// Port to listen on
+        #[arg(short, long, default_value_t = 8000)]
+        port: u16,
+        /// Path to local repository to serve. Default is the one from config file
+        #[arg(short, long)]
+        local_repository: Option<PathBuf>,
+    },
     /// Generate or parse presentation for oca object
     Presentation {
         #[command(subcommand)]
         command: PresentationCommand,
     },
+    /// Run language server for ocafiles of current directory, talking LSP
+    /// over stdio
+    Lsp {},
     /// Launches a terminal user interface application to browse OCA objects
     Tui {
         /// Browse oca objects from directory (recursive)
@@ -143,11 +388,14 @@ enum Commands {
         /// Publishing timeout in seconds. Default is 30.
         #[arg(short, long)]
         timeout: Option<u64>,
+        /// Name of remote repository from config file selected on start
+        #[arg(long)]
+        remote: Option<String>,
     },
     /// Generate json file with all fields of oca object for specified said
     Mapping {
-        #[arg(short, long)]
-        said: String,
+        #[command(flatten)]
+        bundle: BundleSelector,
     },
     /// Returns list of oca objects that uses provided ocafile as dependency
     Deps {
@@ -160,6 +408,31 @@ enum Commands {
     },
 }
 
+/// Opens refn history of local repository. History is optional, so failure
+/// is only reported, e.g. when another process holds the lock.
+fn open_history(local_repository_path: &Path) -> Option<RefnHistory> {
+    match RefnHistory::new(local_repository_path.join(OCA_HISTORY_DIR)) {
+        Ok(history) => Some(history),
+        Err(e) => {
+            eprintln!("Warning: refn history not available: {}", e);
+            None
+        }
+    }
+}
+
+/// Prints history of name (refn), including SAID it points to now.
+fn print_refn_history(
+    facade: &Facade,
+    history: Option<&RefnHistory>,
+    refn: &str,
+) -> Result<(), CliError> {
+    // Without history only current SAID is known, which isn't a history.
+    let history = history.ok_or(CliError::HistoryUnavailable)?;
+    let current = resolve_refn(facade, None, refn).ok();
+    history.print(refn, current.as_ref())?;
+    Ok(())
+}
+
 fn get_oca_facade(local_repository_path: PathBuf) -> Facade {
     let db = create_or_open_local_storage(local_repository_path.join(OCA_REPOSITORY_DIR));
     let cache = create_or_open_local_storage(local_repository_path.join(OCA_CACHE_DB_DIR));
@@ -169,42 +442,6 @@ fn get_oca_facade(local_repository_path: PathBuf) -> Facade {
     Facade::new(Box::new(db.clone()), Box::new(cache), cache_storage_config)
 }
 
-fn saids_to_publish(
-    facade: Arc<Mutex<Facade>>,
-    saids: &[SelfAddressingIdentifier],
-) -> HashSet<SelfAddressingIdentifier> {
-    let mut to_publish = HashSet::new();
-    for said in saids {
-        to_publish.insert(said.clone());
-        let dep_said = dependant_saids(facade.clone(), said);
-        if let Some(deps) = dep_said {
-            let more_deps = saids_to_publish(facade.clone(), &deps);
-            to_publish.extend(more_deps);
-        };
-    }
-    to_publish
-}
-
-fn dependant_saids(
-    facade: Arc<Mutex<Facade>>,
-    said: &SelfAddressingIdentifier,
-) -> Option<Vec<SelfAddressingIdentifier>> {
-    let facade_locked = facade.lock().unwrap();
-    let bundles = facade_locked.get_oca_bundle(said.clone(), true).unwrap();
-    let saids = bundles.dependencies;
-
-    if saids.is_empty() {
-        None
-    } else {
-        Some(
-            saids
-                .iter()
-                .map(|bdle| bdle.said.as_ref().unwrap().clone())
-                .collect::<Vec<_>>(),
-        )
-    }
-}
-
 /// Publish oca bundle pointed by SAID to configured repository
 ///
 /// # Arguments
@@ -217,33 +454,91 @@ fn publish_oca_file_for(
     timeout: &Option<u64>,
     repository_url: Url,
 ) -> Result<(), CliError> {
-    let timeout = timeout.unwrap_or(666);
-    let facade = facade.lock().unwrap();
+    let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+    let ocafile = {
+        let facade = facade.lock().unwrap();
+        facade.get_oca_bundle_ocafile(said.clone(), false)
+    };
 
-    match facade.get_oca_bundle_ocafile(said.clone(), false) {
+    match ocafile {
         Ok(ocafile) => send_to_repo(&repository_url, ocafile, timeout),
         Err(errors) => Err(CliError::PublishError(said, errors)),
     }
 }
 
+/// Fetch oca bundle pointed by SAID from remote repository and build it in
+/// local repository. Referenced bundles missing in local repository are
+/// fetched first.
+///
+/// # Arguments
+/// * `said` - SAID of oca bundle to fetch
+///
+///
+fn fetch_oca_file_for(
+    facade: Arc<Mutex<Facade>>,
+    said: SelfAddressingIdentifier,
+    timeout: &Option<u64>,
+    repository_url: Url,
+) -> Result<(), CliError> {
+    let ocafile = get_from_repo(&repository_url, &said, timeout.unwrap_or(DEFAULT_TIMEOUT))?;
+    for dependency in referenced_saids(&ocafile) {
+        let missing = {
+            let facade_locked = facade.lock().unwrap();
+            facade_locked
+                .get_oca_bundle(dependency.clone(), false)
+                .is_err()
+        };
+        if missing {
+            fetch_oca_file_for(facade.clone(), dependency, timeout, repository_url.clone())?;
+        }
+    }
+
+    let mut facade_locked = facade.lock().unwrap();
+    // Check SAID before building, so mismatching bundle is never stored.
+    let derived = facade_locked
+        .validate_ocafile(ocafile.clone())
+        .map_err(|errors| {
+            CliError::FetchError(
+                said.clone(),
+                errors.iter().map(ToString::to_string).collect(),
+            )
+        })?
+        .oca_bundle
+        .said;
+    if derived.as_ref() != Some(&said) {
+        return Err(CliError::FetchError(
+            said,
+            vec!["Fetched ocafile doesn't match requested SAID".to_string()],
+        ));
+    }
+    match facade_locked.build_from_ocafile(ocafile) {
+        Ok(_) => {
+            println!("Fetched SAID {} from {}", &said, &repository_url);
+            Ok(())
+        }
+        Err(errors) => Err(CliError::FetchError(
+            said,
+            vec![BuildingFailures::from(errors).to_string()],
+        )),
+    }
+}
+
 fn main() -> Result<(), CliError> {
     initialize_logging().unwrap();
 
     App::setup_panic_hooks()?;
     let args = Args::parse();
 
-    // Any command triggered
-    // Check if config exist if not ask user to initialize the repo
-    // or read the config to properly parse all stuff
-    // first check if there is local .oca
-    // then check home dir for global .oca
-    // default is one repo for all in home dir
-    //
-
-    let config = init_or_read_config();
+    // Config is layered: `--config-path`, `OCA_CONFIG`, project `.oca`
+    // (searched upward), home `.oca`, system config, and then `OCA_*`
+    // variables on top. `init` creates new config and `config` subcommands
+    // read the files on their own, so they don't need it.
+    let config = match &args.command {
+        Some(Commands::Init {}) | Some(Commands::Config { command: Some(_) }) => Config::default(),
+        _ => init_or_read_config(args.config_path.as_deref(), args.non_interactive)?,
+    };
     info!("Config: {:?}", config);
-    let local_repository_path = config.local_repository_path;
-    let remote_repo_url_from_config = config.repository_url;
+    let local_repository_path = config.local_repository_path.clone();
 
     let unwind_res = std::panic::catch_unwind(AssertUnwindSafe(|| {
         match &args.command {
@@ -259,14 +554,17 @@ fn main() -> Result<(), CliError> {
                             Ok(it) => Ok(it),
                             Err(err) => {
                                 println!("{}", err);
-                                Err(CliError::WriteFileFailed(err))
+                                Err(CliError::Config(err))
                             }
                         }
                     }
                     Err(err) => Err(CliError::CurrentDirFailed(err)),
                 }
             }
-            Some(Commands::Config {}) => {
+            Some(Commands::Config {
+                command: Some(command),
+            }) => handle_config_command(command, args.config_path.as_deref()),
+            Some(Commands::Config { command: None }) => {
                 info!("Configuration of oca");
                 println!(
                     "Local repository: {:?} ",
@@ -285,26 +583,54 @@ fn main() -> Result<(), CliError> {
                 publish,
                 diff,
                 repository_url,
+                remote,
+                check_compat,
+                allow_breaking,
             }) => {
                 let nodes = load_nodes(ocafile.clone(), directory.as_ref())?;
+                let history = open_history(&local_repository_path);
+                let history = history.as_ref();
                 let facade = Arc::new(Mutex::new(get_oca_facade(local_repository_path)));
+                let previous_refs = facade.lock().unwrap().fetch_all_refs().unwrap_or_default();
+                // Compatibility is checked after building and before
+                // publishing, so breaking changes don't reach remote repository.
+                let compat_check = || -> Result<(), CliError> {
+                    if !*check_compat {
+                        return Ok(());
+                    }
+                    let reports =
+                        compat::check(&facade.lock().unwrap(), &previous_refs, &nodes, history)?;
+                    for report in &reports {
+                        report.print();
+                    }
+                    let breaking = reports.iter().filter(|r| r.is_breaking()).count();
+                    if breaking > 0 && !*allow_breaking {
+                        return Err(CliError::BreakingChanges(breaking));
+                    }
+                    Ok(())
+                };
 
                 match (directory, *publish, *diff) {
                     (None, false, false) => {
                         // No directory, no cache.
                         for node in nodes.iter() {
-                            build::build(facade.clone(), node, None)?;
+                            build::build(facade.clone(), node, None, history)?;
                         }
+                        compat_check()?;
                     }
                     (None, true, false) => {
                         // No directory, no cache.
                         let saids: Result<Vec<_>, _> = nodes
                             .iter()
-                            .filter_map(|node| build::build(facade.clone(), node, None).transpose())
+                            .filter_map(|node| {
+                                build::build(facade.clone(), node, None, history).transpose()
+                            })
                             .collect();
+                        let saids = saids?;
+                        compat_check()?;
                         let remote_repo_url =
-                            load_remote_repo_url(&None, remote_repo_url_from_config)?;
-                        for (said, _refn) in saids? {
+                            load_remote_repo_url(repository_url, remote, &config)?;
+                        for (said, _refn) in saids {
                             println!("Publishing SAID {} to {}", &said, &remote_repo_url);
                             publish_oca_file_for(
                                 facade.clone(),
@@ -316,20 +642,23 @@ fn main() -> Result<(), CliError> {
                     }
                     (Some(directory), true, false) => {
                         let remote_repo_url =
-                            load_remote_repo_url(&repository_url, remote_repo_url_from_config)?;
+                            load_remote_repo_url(repository_url, remote, &config)?;
                         let (_rebuilt_nodes, cache_said) =
-                            rebuild(directory.as_path(), facade.clone(), &nodes)?;
-                        handle_publish(facade, remote_repo_url, &nodes, &cache_said)?;
+                            rebuild(directory.as_path(), facade.clone(), &nodes, history)?;
+                        compat_check()?;
+                        handle_publish(facade.clone(), remote_repo_url, &nodes, &cache_said)?;
                     }
                     (Some(directory), false, _) => {
-                        rebuild(directory.as_path(), facade, &nodes)?;
+                        rebuild(directory.as_path(), facade.clone(), &nodes, history)?;
+                        compat_check()?;
                     }
                     (Some(directory), true, true) => {
                         let remote_repo_url =
-                            load_remote_repo_url(&None, remote_repo_url_from_config)?;
+                            load_remote_repo_url(repository_url, remote, &config)?;
                         let (rebuilt_nodes, cache) =
-                            rebuild(directory.as_path(), facade.clone(), &nodes)?;
-                        handle_publish(facade, remote_repo_url, &rebuilt_nodes, &cache)?;
+                            rebuild(directory.as_path(), facade.clone(), &nodes, history)?;
+                        compat_check()?;
+                        handle_publish(facade.clone(), remote_repo_url, &rebuilt_nodes, &cache)?;
                     }
                     (None, true, true) => {
                         println!("Error: --diff is only available with -d or --directory option");
@@ -344,61 +673,54 @@ fn main() -> Result<(), CliError> {
 
             Some(Commands::Publish {
                 repository_url,
+                remote,
+                from_pack: Some(pack_path),
+                timeout,
+                ..
+            }) => {
+                info!("Publish OCA bundles from pack file to repository");
+                let pack = Pack::load(pack_path)?;
+                let remote_repo_url = load_remote_repo_url(repository_url, remote, &config)?;
+                pack.publish(&remote_repo_url, timeout)
+            }
+            Some(Commands::Publish {
+                repository_url,
+                remote,
                 said,
                 timeout,
                 diff,
                 directory,
                 all,
+                jobs,
+                from_pack: None,
             }) => match (said, directory, diff, all) {
                 (Some(said), None, false, _) => {
                     info!("Publish OCA bundle and its dependencies to repository");
+                    let history = open_history(&local_repository_path);
                     let facade = get_oca_facade(local_repository_path);
+                    let said = resolve_said(&facade, history.as_ref(), said)?;
                     let facade = Arc::new(Mutex::new(facade));
-                    match SelfAddressingIdentifier::from_str(said) {
-                        Ok(said) => {
-                            // Find dependant saids for said.
-                            let saids_to_publish =
-                                saids_to_publish(facade.clone(), &[said.clone()]);
-                            let remote_repo_url =
-                                load_remote_repo_url(repository_url, remote_repo_url_from_config)?;
-                            // Make post request for all saids
-                            let res: Vec<_> = saids_to_publish
-                                .iter()
-                                .flat_map(|said| {
-                                    println!("Publishing {} to {}", &said, &remote_repo_url);
-                                    match publish_oca_file_for(
-                                        facade.clone(),
-                                        said.clone(),
-                                        timeout,
-                                        remote_repo_url.clone(),
-                                    ) {
-                                        Ok(_) => {
-                                            vec![]
-                                        }
-                                        Err(err) => vec![err.to_string()],
-                                    }
-                                })
-                                .collect();
-                            if res.is_empty() {
-                                Ok(())
-                            } else {
-                                Err(CliError::PublishError(said, res))
-                            }
-                        }
-                        Err(err) => {
-                            println!("Invalid SAID: {}", err);
-                            Err(err.into())
-                        }
+                    // Find dependant saids for said, in order of publishing.
+                    let batches = publish_batches(facade.clone(), std::slice::from_ref(&said))?;
+                    let remote_repo_url = load_remote_repo_url(repository_url, remote, &config)?;
+                    // Make post request for all saids
+                    let res = publish_in_order(facade, &batches, *jobs, timeout, &remote_repo_url);
+                    if res.is_empty() {
+                        Ok(())
+                    } else {
+                        Err(CliError::PublishError(said, res))
                     }
                 }
                 (None, Some(directory), false, _) => {
                     let nodes = load_nodes(None, Some(directory))?;
+                    let history = open_history(&local_repository_path);
+                    let history = history.as_ref();
                     let facade =
                         Arc::new(Mutex::new(get_oca_facade(local_repository_path.clone())));
                     let (_rebuilt_nodes, said_cache) =
-                        rebuild(directory.as_path(), facade.clone(), &nodes)?;
+                        rebuild(directory.as_path(), facade.clone(), &nodes, history)?;
 
-                    let remote_repo_url = load_remote_repo_url(&None, remote_repo_url_from_config)?;
+                    let remote_repo_url = load_remote_repo_url(repository_url, remote, &config)?;
 
                     // Publish all elements in directory
                     handle_publish(facade, remote_repo_url, &nodes, &said_cache)?;
@@ -406,12 +728,14 @@ fn main() -> Result<(), CliError> {
                 }
                 (None, Some(directory), true, false) => {
                     let nodes = load_nodes(None, Some(directory))?;
+                    let history = open_history(&local_repository_path);
+                    let history = history.as_ref();
                     let facade =
                         Arc::new(Mutex::new(get_oca_facade(local_repository_path.clone())));
                     let (rebuilt_nodes, said_cache) =
-                        rebuild(directory.as_path(), facade.clone(), &nodes)?;
+                        rebuild(directory.as_path(), facade.clone(), &nodes, history)?;
 
-                    let remote_repo_url = load_remote_repo_url(&None, remote_repo_url_from_config)?;
+                    let remote_repo_url = load_remote_repo_url(repository_url, remote, &config)?;
 
                     // Publish only rebuilt elements in directory
                     handle_publish(facade, remote_repo_url, &rebuilt_nodes, &said_cache)?;
@@ -431,81 +755,227 @@ fn main() -> Result<(), CliError> {
                 }
                 _ => unreachable!(),
             },
-            Some(Commands::List {}) => {
+            Some(Commands::Fetch {
+                said,
+                repository_url,
+                remote,
+                timeout,
+            }) => {
+                info!("Fetch OCA bundle and its dependencies from repository");
+                let said = SelfAddressingIdentifier::from_str(said)?;
+                let remote_repo_url = load_remote_repo_url(repository_url, remote, &config)?;
+                let facade = Arc::new(Mutex::new(get_oca_facade(local_repository_path)));
+                fetch_oca_file_for(facade, said, timeout, remote_repo_url)
+            }
+            Some(Commands::Diff { from, to, format }) => {
+                let history = open_history(&local_repository_path);
+                let facade = get_oca_facade(local_repository_path);
+                let from = resolve_said(&facade, history.as_ref(), from)?;
+                let to = resolve_said(&facade, history.as_ref(), to)?;
+                let diff = diff(&facade, &from, &to)?;
+                println!("{}", diff.format(*format));
+                Ok(())
+            }
+            Some(Commands::List {
+                filter,
+                format,
+                limit,
+                offset,
+            }) => {
                 info!(
                     "List OCA object from local repository: {:?}",
                     local_repository_path
                 );
                 let facade = get_oca_facade(local_repository_path);
-                let mut page = 1;
-                let page_size = 20;
-                let mut result = facade.fetch_all_oca_bundle(page_size, page).unwrap();
-                let meta = result.metadata;
-                let mut count = 0;
-                info!("Found {} objects in repo", meta.total);
-                let refs = facade.fetch_all_refs().unwrap();
-                info!("Found {:#?} refs in repo", refs);
-                loop {
-                    info!("Processing page: {}, count: {}", page, count);
-                    if count >= meta.total {
-                        break;
-                    }
-                    let records = result.records;
-                    count += records.len();
-                    info!("Processing {} objects", records.len());
-                    for bundle in records {
-                        let said = bundle.said.unwrap();
-                        let matching_ref = refs.iter().find(|&(_, v)| *v == said.to_string());
-                        match matching_ref {
-                            Some((refs, _)) => {
-                                println!("SAID: {}, name: {}", said, refs);
-                            }
-                            None => {
-                                println!("SAID: {}", said);
-                            }
-                        }
+                let entries = list_bundles(&facade, filter, *limit, *offset)?;
+                println!("{}", format_entries(&entries, *format));
+                Ok(())
+            }
+            Some(Commands::Export {
+                command: Some(command),
+                ..
+            }) => handle_export_command(command, &local_repository_path),
+            Some(Commands::Export {
+                command: None,
+                said,
+                with_dependencies,
+                output,
+            }) => {
+                let (said, output) = match (said, output) {
+                    (Some(said), Some(output)) => (said, output),
+                    _ => unreachable!("Said and output are required without subcommand"),
+                };
+                let said = SelfAddressingIdentifier::from_str(said)?;
+                let facade = Arc::new(Mutex::new(get_oca_facade(local_repository_path)));
+                let pack = Pack::from_repository(facade, said, *with_dependencies)?;
+                pack.save(output)?;
+                println!(
+                    "Exported {} OCA bundles to {}",
+                    pack.manifest.len(),
+                    output.display()
+                );
+                Ok(())
+            }
+            Some(Commands::Import {
+                command: Some(command),
+                ..
+            }) => handle_import_command(command),
+            Some(Commands::Import {
+                command: None,
+                pack,
+            }) => {
+                let pack = match pack {
+                    Some(pack) => pack,
+                    None => unreachable!("Pack is required without subcommand"),
+                };
+                let pack = Pack::load(pack)?;
+                let facade = Arc::new(Mutex::new(get_oca_facade(local_repository_path)));
+                pack.import(facade)
+            }
+            Some(Commands::Data { command }) => {
+                handle_data_command(command, &local_repository_path)
+            }
+            Some(Commands::Repo { command }) => {
+                handle_repo_command(command, &local_repository_path)
+            }
+            Some(Commands::Doctor { repair }) => {
+                let report = doctor::check(&local_repository_path)?;
+                report.print();
+                if !*repair {
+                    return match report.problems.len() {
+                        0 => Ok(()),
+                        problems => Err(CliError::IntegrityError(problems)),
+                    };
+                }
+                let rebuilt = doctor::repair(&local_repository_path, &report)?;
+                println!("Rebuilt index with {} OCA bundles", rebuilt);
+                let remaining = doctor::check(&local_repository_path)?.problems;
+                for problem in &remaining {
+                    println!("Not repaired: {}", problem);
+                }
+                match remaining.len() {
+                    0 => Ok(()),
+                    problems => Err(CliError::IntegrityError(problems)),
+                }
+            }
+            Some(Commands::Gc { dry_run, keep }) => {
+                let history = open_history(&local_repository_path);
+                let garbage = find_garbage(&local_repository_path, history.as_ref(), keep)?;
+                if garbage.saids.is_empty() {
+                    println!("Nothing to delete. Kept {} OCA bundles", garbage.kept());
+                    return Ok(());
+                }
+                if *dry_run {
+                    println!("Would delete {} OCA bundles:", garbage.saids.len());
+                    for said in &garbage.saids {
+                        println!("\t{}", said);
                     }
-                    page += 1;
-                    result = facade.fetch_all_oca_bundle(page_size, page).unwrap();
+                    return Ok(());
+                }
+                collect(&local_repository_path, &garbage)?;
+                println!(
+                    "Deleted {} OCA bundles. Kept {} OCA bundles",
+                    garbage.saids.len(),
+                    garbage.kept()
+                );
+                Ok(())
+            }
+            Some(Commands::Serve {
+                port,
+                local_repository,
+            }) => {
+                let local_repository_path =
+                    local_repository.clone().unwrap_or(local_repository_path);
+                let facade = Arc::new(Mutex::new(get_oca_facade(local_repository_path.clone())));
+                let server = RepositoryServer::bind(("127.0.0.1", *port), facade)?;
+                if let Some(url) = server.url() {
+                    println!(
+                        "Serving local repository {} at {}",
+                        local_repository_path.display(),
+                        url
+                    );
                 }
+                server.run();
                 Ok(())
             }
             Some(Commands::Show {
+                bundle,
+                history: true,
+                ..
+            })
+            | Some(Commands::Get {
+                bundle,
+                history: true,
+                ..
+            }) => {
+                let history = open_history(&local_repository_path);
+                let facade = get_oca_facade(local_repository_path);
+                let refn = bundle.refn(&facade).ok_or(CliError::MissingName(
+                    bundle.resolve(&facade, history.as_ref())?,
+                ))?;
+                print_refn_history(&facade, history.as_ref(), &refn)
+            }
+            Some(Commands::Log { refn }) => {
+                let history = open_history(&local_repository_path);
+                let facade = get_oca_facade(local_repository_path);
+                print_refn_history(&facade, history.as_ref(), refn)
+            }
+            Some(Commands::Tag {
+                refn,
+                version,
                 said,
+                force,
+            }) => {
+                let history =
+                    open_history(&local_repository_path).ok_or(CliError::HistoryUnavailable)?;
+                let facade = get_oca_facade(local_repository_path);
+                let current = resolve_refn(&facade, None, refn).ok();
+                let said = match said {
+                    Some(said) => resolve_said(&facade, Some(&history), said)?,
+                    None => current
+                        .clone()
+                        .ok_or(CliError::OCABundleRefnNotFound(refn.clone()))?,
+                };
+                // Refn could be built before history was recorded.
+                if current.as_ref() == Some(&said) {
+                    history.record(refn, HistoryEntry::untracked(said.clone()))?;
+                }
+                history.tag(refn, version, &said, *force)?;
+                println!("Tagged {} as {}@{}", said, refn, version);
+                Ok(())
+            }
+            Some(Commands::Show {
+                bundle,
                 ast,
                 dereference,
+                history: false,
             }) => {
                 info!("Search for OCA object in local repository");
+                let history = open_history(&local_repository_path);
                 let facade = get_oca_facade(local_repository_path);
-                match SelfAddressingIdentifier::from_str(said) {
-                    Ok(said) => {
-                        if *ast {
-                            let oca_ast = facade
-                                .get_oca_bundle_ast(said)
-                                .map_err(CliError::OcaBundleAstError)?;
-                            serde_json::to_writer_pretty(std::io::stdout(), &oca_ast)
-                                .expect("Faild to format oca ast");
-                            Ok(())
-                        } else {
-                            let ocafile = facade
-                                .get_oca_bundle_ocafile(said, *dereference)
-                                .map_err(CliError::OcaBundleAstError)?;
-                            println!("{}", ocafile);
-                            Ok(())
-                        }
-                    }
-                    Err(err) => {
-                        println!("Invalid SAID: {}", err);
-                        Err(CliError::InvalidSaid(err))
-                    }
+                let said = bundle.resolve(&facade, history.as_ref())?;
+                if *ast {
+                    let oca_ast = facade
+                        .get_oca_bundle_ast(said)
+                        .map_err(CliError::OcaBundleAstError)?;
+                    serde_json::to_writer_pretty(std::io::stdout(), &oca_ast)
+                        .expect("Faild to format oca ast");
+                } else {
+                    let ocafile = facade
+                        .get_oca_bundle_ocafile(said, *dereference)
+                        .map_err(CliError::OcaBundleAstError)?;
+                    println!("{}", ocafile);
                 }
+                Ok(())
             }
             Some(Commands::Get {
-                said,
+                bundle,
                 with_dependencies,
+                history: false,
             }) => {
+                let history = open_history(&local_repository_path);
                 let facade = get_oca_facade(local_repository_path);
-                let said = SelfAddressingIdentifier::from_str(said)?;
+                let said = bundle.resolve(&facade, history.as_ref())?;
                 let oca_bundles = facade
                     .get_oca_bundle(said, *with_dependencies)
                     .map_err(CliError::OcaBundleAstError)?;
@@ -518,9 +988,10 @@ fn main() -> Result<(), CliError> {
             }
             Some(Commands::Presentation { command }) => {
                 match command {
-                    PresentationCommand::Generate { said, format } => {
-                        let said = SelfAddressingIdentifier::from_str(said)?;
+                    PresentationCommand::Generate { bundle, format } => {
+                        let history = open_history(&local_repository_path);
                         let facade = get_oca_facade(local_repository_path);
+                        let said = bundle.resolve(&facade, history.as_ref())?;
                         let presentation = handle_generate(said, &facade)?;
                         let wrapped_presentation = WrappedPresentation { presentation };
                         let output = match format {
@@ -536,27 +1007,7 @@ fn main() -> Result<(), CliError> {
                         format,
                         recalculate,
                     } => {
-                        let ext = from_file.extension();
-                        let extension = match ext {
-                            Some(ext) => match ext.to_str() {
-                                Some(ext) => Format::from_str(ext)
-                                    .map_err(|e| CliError::FileExtensionError(e.to_string())),
-                                None => Err(CliError::FileExtensionError(
-                                    "Unsupported file extension".to_string(),
-                                )),
-                            },
-                            None => {
-                                warn!("Missing input file extension. Using JSON");
-                                Ok(Format::JSON)
-                            }
-                        }?;
-
-                        let file_contents = fs::read_to_string(from_file)
-                            .map_err(|e| CliError::ReadFileFailed(from_file.clone(), e))?;
-                        let pres: WrappedPresentation = match extension {
-                            Format::JSON => serde_json::from_str(&file_contents).unwrap(),
-                            Format::YAML => serde_yaml::from_str(&file_contents).unwrap(),
-                        };
+                        let (extension, pres) = load_presentation(from_file)?;
                         let pres = handle_validate(pres.presentation, *recalculate);
                         match pres {
                             Ok(pres) => {
@@ -608,18 +1059,44 @@ fn main() -> Result<(), CliError> {
                         };
                         Ok(())
                     }
+                    PresentationCommand::Publish {
+                        from_file,
+                        repository_url,
+                        remote,
+                        timeout,
+                    } => {
+                        let (_, pres) = load_presentation(from_file)?;
+                        let facade = get_oca_facade(local_repository_path);
+                        let remote_repo_url =
+                            load_remote_repo_url(repository_url, remote, &config)?;
+                        let said = presentation_command::handle_publish(
+                            pres.presentation,
+                            &facade,
+                            &remote_repo_url,
+                            config.presentation_endpoint(),
+                            timeout.unwrap_or(DEFAULT_TIMEOUT),
+                        )?;
+                        println!("Published presentation {} to {}", said, remote_repo_url);
+                        Ok(())
+                    }
                 }
             }
-            Some(Commands::Validate { ocafile, directory }) => {
+            Some(Commands::Validate {
+                ocafile,
+                directory,
+                format,
+            }) => {
                 let paths = match (ocafile, directory) {
                     (None, None) => unreachable!("At least one argument expected"),
                     (_, Some(dir)) => visit_dirs_recursive(dir)?,
                     (Some(oca_file), None) => oca_file.clone(),
                 };
+                let text = *format == ValidateFormat::Text;
 
                 let facade = get_oca_facade(local_repository_path);
                 let facade = Arc::new(Mutex::new(facade));
-                let mut graph = MutableGraph::new(paths)?;
+                let mut graph = MutableGraph::new(paths.clone())?;
+                let mut all_errs = vec![];
                 match ocafile {
                     Some(oca_file) => {
                         let mut cache = HashSet::new();
@@ -637,7 +1114,9 @@ fn main() -> Result<(), CliError> {
                                 }) => (),
                                 Err(e) => return Err(e.into()),
                             };
-                            println!("Validating {}", &node.refn);
+                            if text {
+                                println!("Validating {}", &node.refn);
+                            }
                             let (out_cache, errs) = validate::validate_directory(
                                 facade.clone(),
                                 &mut graph,
@@ -645,9 +1124,7 @@ fn main() -> Result<(), CliError> {
                                 &cache,
                             )?;
                             cache.extend(out_cache);
-                            for err in errs {
-                                println!("{}", err)
-                            }
+                            all_errs.extend(errs);
                         }
                     }
                     None => {
@@ -657,22 +1134,146 @@ fn main() -> Result<(), CliError> {
                             None,
                             &HashSet::new(),
                         )?;
+                        all_errs.extend(errs);
+                    }
+                };
 
-                        for err in errs {
-                            println!("{}", err)
+                match format_report(*format, &paths, &all_errs) {
+                    Some(report) => println!("{}", report),
+                    None => {
+                        for err in all_errs {
+                            println!("{}", render(&err).unwrap_or_else(|| err.to_string()))
                         }
                     }
+                }
+                Ok(())
+            }
+            Some(Commands::Lint {
+                ocafile,
+                directory,
+                list_rules,
+            }) => {
+                let severity = |rule: &lint::Rule| {
+                    config
+                        .lint
+                        .get(rule.id)
+                        .copied()
+                        .unwrap_or(rule.default_severity)
                 };
+                if *list_rules {
+                    for rule in lint::RULES {
+                        println!("{} ({}): {}", rule.id, severity(rule), rule.description);
+                    }
+                    return Ok(());
+                }
+                let mut paths = ocafile.clone().unwrap_or_default();
+                if let Some(dir) = directory {
+                    paths.extend(visit_dirs_recursive(dir)?);
+                }
 
+                let facade = get_oca_facade(local_repository_path);
+                let mut graph = MutableGraph::new(paths.clone())?;
+                let mut errors = 0;
+                for path in &paths {
+                    match lint::lint_file(&facade, &mut graph, path, &config.lint) {
+                        Ok(issues) => {
+                            for issue in issues {
+                                if issue.severity == lint::Severity::Error {
+                                    errors += 1;
+                                }
+                                println!("{}", issue.format(path));
+                            }
+                        }
+                        Err(e) => {
+                            errors += 1;
+                            println!("{}", render(&e).unwrap_or_else(|| e.to_string()));
+                        }
+                    }
+                }
+                if errors > 0 {
+                    return Err(CliError::LintErrors(errors));
+                }
+                Ok(())
+            }
+            Some(Commands::Coverage { directory, require }) => {
+                let required = require
+                    .iter()
+                    .map(|code| {
+                        coverage::normalize_language(code)
+                            .ok_or_else(|| CliError::UnknownLanguage(code.clone()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let paths = visit_dirs_recursive(directory)?;
+                let facade = get_oca_facade(local_repository_path);
+                let (rows, incomplete) = coverage::coverage(&facade, &paths, &required)?;
+                println!("{}", coverage::format_coverage(&rows));
+                if incomplete > 0 {
+                    return Err(CliError::IncompleteCoverage(incomplete));
+                }
+                Ok(())
+            }
+            Some(Commands::Fmt {
+                ocafile,
+                directory,
+                check,
+            }) => {
+                let paths = match (ocafile, directory) {
+                    (Some(oca_file), _) => oca_file.clone(),
+                    (None, Some(dir)) => visit_dirs_recursive(dir)?,
+                    (None, None) => visit_dirs_recursive(Path::new("."))?,
+                };
+                let facade = get_oca_facade(local_repository_path);
+                let mut graph = MutableGraph::new(paths.clone())?;
+                let mut unformatted = 0;
+                let mut failed = 0;
+                for path in &paths {
+                    let formatted = match fmt::format_file(&facade, &mut graph, path) {
+                        Ok(formatted) => formatted,
+                        Err(e) => {
+                            failed += 1;
+                            println!("{}", render(&e).unwrap_or_else(|| e.to_string()));
+                            continue;
+                        }
+                    };
+                    if let Some(formatted) = formatted {
+                        unformatted += 1;
+                        if *check {
+                            println!("Not formatted: {}", path.display());
+                        } else {
+                            fs::write(path, formatted).map_err(CliError::WriteFileFailed)?;
+                            println!("Formatted {}", path.display());
+                        }
+                    }
+                }
+                if failed > 0 {
+                    return Err(CliError::FormatFailed(failed));
+                }
+                if *check && unformatted > 0 {
+                    return Err(CliError::UnformattedFiles(unformatted));
+                }
                 Ok(())
             }
-            Some(Commands::Tui { dir, timeout }) => {
+            Some(Commands::Lsp {}) => {
+                let root = env::current_dir().map_err(CliError::CurrentDirFailed)?;
+                let facade = get_oca_facade(local_repository_path);
+                let mut server = lsp::LanguageServer::new(root, facade)?;
+                let stdin = std::io::stdin();
+                server
+                    .run(stdin.lock(), std::io::stdout().lock())
+                    .map_err(CliError::LspError)
+            }
+            Some(Commands::Tui {
+                dir,
+                timeout,
+                remote,
+            }) => {
                 if let Some(directory) = dir.as_ref() {
                     let all_oca_files =
                         load_ocafiles_all(None, Some(directory)).unwrap_or_else(|err| {
                             eprintln!("{err}");
                             process::exit(1);
                         });
+                    let history = open_history(&local_repository_path);
                     let facade = Arc::new(Mutex::new(get_oca_facade(local_repository_path)));
 
                     let to_show = visit_current_dir(directory)?
@@ -683,8 +1284,10 @@ fn main() -> Result<(), CliError> {
                         to_show,
                         all_oca_files,
                         facade,
-                        remote_repo_url_from_config,
+                        config.remotes(),
+                        remote.clone().or(config.default_remote_name()),
                         *timeout,
+                        history,
                     )
                     .unwrap_or_else(|err| {
                         eprintln!("{err}");
@@ -696,11 +1299,12 @@ fn main() -> Result<(), CliError> {
                     process::exit(1);
                 }
             }
-            Some(Commands::Mapping { said }) => {
-                let said = SelfAddressingIdentifier::from_str(said)?;
+            Some(Commands::Mapping { bundle }) => {
                 let paths = load_ocafiles_all(None, Some(&local_repository_path))?;
 
+                let history = open_history(&local_repository_path);
                 let facade = get_oca_facade(local_repository_path);
+                let said = bundle.resolve(&facade, history.as_ref())?;
 
                 let graph = DependencyGraph::from_paths(paths).unwrap();
 
@@ -729,7 +1333,10 @@ fn main() -> Result<(), CliError> {
     match unwind_res {
         Ok(Ok(_)) => Ok(()),
         Ok(Err(er)) => {
-            eprintln!("Error: {}", er);
+            eprintln!(
+                "{}",
+                render(&er).unwrap_or_else(|| format!("Error: {}", er))
+            );
             std::process::exit(1);
         }
         Err(panic) => {
@@ -744,6 +1351,33 @@ pub struct WrappedPresentation {
     presentation: Presentation,
 }
 
+/// Reads presentation from file. Format is chosen basing on file extension.
+fn load_presentation(from_file: &PathBuf) -> Result<(Format, WrappedPresentation), CliError> {
+    let ext = from_file.extension();
+    let extension = match ext {
+        Some(ext) => match ext.to_str() {
+            Some(ext) => {
+                Format::from_str(ext).map_err(|e| CliError::FileExtensionError(e.to_string()))
+            }
+            None => Err(CliError::FileExtensionError(
+                "Unsupported file extension".to_string(),
+            )),
+        },
+        None => {
+            warn!("Missing input file extension. Using JSON");
+            Ok(Format::JSON)
+        }
+    }?;
+
+    let file_contents = fs::read_to_string(from_file)
+        .map_err(|e| CliError::ReadFileFailed(from_file.clone(), e))?;
+    let pres: WrappedPresentation = match extension {
+        Format::JSON => serde_json::from_str(&file_contents).map_err(PresentationError::from)?,
+        Format::YAML => serde_yaml::from_str(&file_contents).map_err(PresentationError::from)?,
+    };
+    Ok((extension, pres))
+}
+
 // ocafile build -i OCAfile
 // ocafile build -s scid
 // ocafile publish
@@ -0,0 +1,203 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use oca_rs::Facade;
+use said::SelfAddressingIdentifier;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    doctor::{build_named, names_by_said},
+    error::CliError,
+    publish::publish_batches,
+    utils::{send_to_repo, DEFAULT_TIMEOUT},
+};
+
+const PACK_VERSION: u32 = 1;
+
+/// Set of ocafiles that can be transferred to another machine without access
+/// to repository. Ocafiles are dereferenced, so they refer to each other only
+/// by SAIDs.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Pack {
+    pub version: u32,
+    /// SAIDs of OCA bundles in order of dependencies. Each bundle comes after
+    /// the ones it depends on.
+    pub manifest: Vec<SelfAddressingIdentifier>,
+    /// Ocafiles of OCA bundles, by SAID.
+    pub ocafiles: BTreeMap<String, String>,
+    /// Names (refn) of OCA bundles, by SAID.
+    #[serde(default)]
+    pub names: BTreeMap<String, Vec<String>>,
+}
+
+impl Pack {
+    /// Collects ocafile of OCA bundle of given SAID, and optionally ocafiles
+    /// of all its dependencies.
+    pub fn from_repository(
+        facade: Arc<Mutex<Facade>>,
+        said: SelfAddressingIdentifier,
+        with_dependencies: bool,
+    ) -> Result<Self, CliError> {
+        let manifest = if with_dependencies {
+            publish_batches(facade.clone(), &[said])?
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+        } else {
+            vec![said]
+        };
+
+        let facade = facade.lock().unwrap();
+        let mut refs = names_by_said(&facade.fetch_all_refs().unwrap_or_default());
+        let mut ocafiles = BTreeMap::new();
+        let mut names = BTreeMap::new();
+        for said in &manifest {
+            let ocafile = facade
+                .get_oca_bundle_ocafile(said.clone(), true)
+                .map_err(CliError::OcaBundleAstError)?;
+            ocafiles.insert(said.to_string(), ocafile);
+            if let Some(refns) = refs.remove(&said.to_string()) {
+                names.insert(said.to_string(), refns);
+            }
+        }
+
+        Ok(Self {
+            version: PACK_VERSION,
+            manifest,
+            ocafiles,
+            names,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, CliError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| CliError::ReadFileFailed(path.to_owned(), e))?;
+        let pack: Pack = serde_json::from_str(&contents).map_err(CliError::ReadOcaError)?;
+        if pack.version != PACK_VERSION {
+            return Err(CliError::PackError(format!(
+                "Unsupported pack version: {}",
+                pack.version
+            )));
+        }
+        Ok(pack)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CliError> {
+        let contents = serde_json::to_string_pretty(self).map_err(CliError::WriteOcaError)?;
+        fs::write(path, contents).map_err(CliError::WriteFileFailed)
+    }
+
+    /// Returns ocafiles in order of dependencies.
+    pub fn ocafiles(
+        &self,
+    ) -> impl Iterator<Item = Result<(&SelfAddressingIdentifier, &str), CliError>> {
+        self.manifest.iter().map(|said| {
+            self.ocafiles
+                .get(&said.to_string())
+                .map(|ocafile| (said, ocafile.as_str()))
+                .ok_or(CliError::PackError(format!(
+                    "Missing ocafile for SAID {}",
+                    said
+                )))
+        })
+    }
+
+    /// Checks if SAIDs of all ocafiles match the manifest and builds them in
+    /// local repository under all their names. Bundle is stored only after
+    /// its SAID is checked.
+    pub fn import(&self, facade: Arc<Mutex<Facade>>) -> Result<(), CliError> {
+        for el in self.ocafiles() {
+            let (said, ocafile) = el?;
+            let refns = self
+                .names
+                .get(&said.to_string())
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            build_named(&mut facade.lock().unwrap(), said, ocafile, refns)?;
+            println!("Imported OCA bundle with SAID: {}", said);
+        }
+        Ok(())
+    }
+
+    /// Sends all ocafiles to remote repository in order of dependencies.
+    pub fn publish(&self, repository_url: &Url, timeout: &Option<u64>) -> Result<(), CliError> {
+        for el in self.ocafiles() {
+            let (said, ocafile) = el?;
+            println!("Publishing {} to {}", said, repository_url);
+            send_to_repo(
+                repository_url,
+                ocafile.to_string(),
+                timeout.unwrap_or(DEFAULT_TIMEOUT),
+            )
+            .map_err(|e| CliError::PublishError(said.clone(), vec![e.to_string()]))?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_export_and_import() -> anyhow::Result<()> {
+    use crate::get_oca_facade;
+    use oca_rs::facade::bundle::BundleElement;
+
+    let tmp_dir = tempdir::TempDir::new("pack")?;
+    let mut facade = get_oca_facade(tmp_dir.path().join("source"));
+    facade
+        .build_from_ocafile("-- name=first\nADD ATTRIBUTE name=Text".to_string())
+        .unwrap();
+    let second = match facade
+        .build_from_ocafile("-- name=second\nADD ATTRIBUTE first=refn:first".to_string())
+        .unwrap()
+    {
+        BundleElement::Mechanics(bundle) => bundle.said.unwrap(),
+        BundleElement::Transformation(_) => unreachable!(),
+    };
+    facade
+        .build_from_ocafile("-- name=alias\nADD ATTRIBUTE first=refn:first".to_string())
+        .unwrap();
+    let source_refs = facade.fetch_all_refs().unwrap();
+    let pack = Pack::from_repository(Arc::new(Mutex::new(facade)), second.clone(), true)?;
+    assert_eq!(pack.manifest.len(), 2);
+    assert_eq!(pack.manifest.last(), Some(&second));
+    assert_eq!(pack.names[&second.to_string()], vec!["alias", "second"]);
+
+    let path = tmp_dir.path().join("bundles.ocapack");
+    pack.save(&path)?;
+    let loaded = Pack::load(&path)?;
+
+    let target = Arc::new(Mutex::new(get_oca_facade(tmp_dir.path().join("target"))));
+    loaded.import(target.clone())?;
+    assert_eq!(
+        target.lock().unwrap().fetch_all_refs().unwrap(),
+        source_refs
+    );
+
+    // Tampered ocafile isn't stored.
+    let mut tampered = Pack::load(&path)?;
+    let tampered_ocafile = "ADD ATTRIBUTE other=Text".to_string();
+    tampered
+        .ocafiles
+        .insert(second.to_string(), tampered_ocafile.clone());
+    let target = Arc::new(Mutex::new(get_oca_facade(tmp_dir.path().join("tampered"))));
+    assert!(matches!(
+        tampered.import(target.clone()),
+        Err(CliError::SaidMismatch { .. })
+    ));
+    let mut target = target.lock().unwrap();
+    let tampered_said = target
+        .validate_ocafile(tampered_ocafile)
+        .unwrap()
+        .oca_bundle
+        .said
+        .unwrap();
+    assert!(target.get_oca_bundle(tampered_said, false).is_err());
+    let refs = target.fetch_all_refs().unwrap();
+    assert!(!refs.contains_key("second") && !refs.contains_key("alias"));
+
+    Ok(())
+}
@@ -23,14 +23,16 @@ use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::str::FromStr;
 use thiserror::Error;
+use url::Url;
+
+use crate::utils::{exists_in_repo, post_to_repo, BundleSelector};
 
 #[derive(Subcommand)]
 pub enum PresentationCommand {
     /// Generate presentation for OCA bundle of provided SAID
     Generate {
-        /// SAID of OCA Bundle
-        #[arg(short, long)]
-        said: String,
+        #[command(flatten)]
+        bundle: BundleSelector,
         /// Presentation output format: json or yaml. Default is json
         #[arg(short, long)]
         format: Option<Format>,
@@ -52,6 +54,19 @@ pub enum PresentationCommand {
         #[arg(long, short, default_value_t = false)]
         recalculate: bool,
     },
+    /// Validate presentation from file and publish it into online repository
+    Publish {
+        /// Path to input file
+        #[arg(short, long)]
+        from_file: PathBuf,
+        #[arg(short, long)]
+        repository_url: Option<String>,
+        /// Name of remote repository from config file to publish to
+        #[arg(long, conflicts_with = "repository_url")]
+        remote: Option<String>,
+        #[arg(short, long)]
+        timeout: Option<u64>,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -101,6 +116,34 @@ pub fn handle_validate(
     }
 }
 
+/// Validates presentation digest, checks that presented OCA bundle is known
+/// locally or in remote repository and sends presentation to the remote
+/// repository.
+pub fn handle_publish(
+    pres: Presentation,
+    facade: &Facade,
+    repository_url: &Url,
+    endpoint: &str,
+    timeout: u64,
+) -> Result<SelfAddressingIdentifier, super::CliError> {
+    let pres = handle_validate(pres, false)?;
+    let bundle_digest = pres.bundle_digest.clone();
+    let known_locally = facade.get_oca_bundle(bundle_digest.clone(), false).is_ok();
+    if !known_locally && !exists_in_repo(repository_url, &bundle_digest, timeout)? {
+        return Err(PresentationError::UnknownBundle(bundle_digest).into());
+    }
+    let said = pres.said.clone().ok_or(PresentationError::MissingSaid)?;
+    let payload = serde_json::to_string(&pres).map_err(PresentationError::InvalidJson)?;
+    post_to_repo(
+        repository_url,
+        endpoint,
+        payload,
+        Some("application/json"),
+        timeout,
+    )?;
+    Ok(said)
+}
+
 pub fn handle_generate(
     said: SelfAddressingIdentifier,
     facade: &Facade,
@@ -258,6 +301,10 @@ pub enum PresentationError {
     OcaBundleErrors(Vec<String>),
     #[error("Missing dependency to oca bundle of said {0}")]
     MissingDependency(SelfAddressingIdentifier),
+    #[error("OCA bundle {0} not found in local nor remote repository")]
+    UnknownBundle(SelfAddressingIdentifier),
+    #[error("Presentation has no SAID")]
+    MissingSaid,
     #[error(transparent)]
     Presentation(#[from] presentation::PresentationError),
 }
@@ -0,0 +1,198 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use oca_rs::Facade;
+use said::SelfAddressingIdentifier;
+use url::Url;
+
+use crate::{
+    dependency_graph::GraphError, error::CliError, publish_oca_file_for, utils::reference_said,
+};
+
+/// Collects given OCA bundles and all their dependencies, and groups them into
+/// batches. Bundles of one batch don't depend on each other, and each batch
+/// depends only on bundles from previous batches.
+pub fn publish_batches(
+    facade: Arc<Mutex<Facade>>,
+    saids: &[SelfAddressingIdentifier],
+) -> Result<Vec<Vec<SelfAddressingIdentifier>>, CliError> {
+    let facade = facade.lock().unwrap();
+    let refs = facade.fetch_all_refs().unwrap_or_default();
+    let mut dependencies = HashMap::new();
+    for said in saids {
+        let bundles = facade
+            .get_oca_bundle(said.clone(), true)
+            .map_err(|e| CliError::PublishError(said.clone(), e))?;
+        for bundle in std::iter::once(bundles.bundle).chain(bundles.dependencies) {
+            let bundle_said = bundle.said.clone().ok_or_else(|| {
+                CliError::PublishError(said.clone(), vec!["OCA bundle has no SAID".to_string()])
+            })?;
+            let references = bundle
+                .capture_base
+                .attributes
+                .values()
+                .filter_map(|attr| reference_said(attr, &refs))
+                .collect::<Vec<_>>();
+            dependencies.entry(bundle_said).or_insert(references);
+        }
+    }
+    Ok(batches(&dependencies)?)
+}
+
+/// Publishes batches one after another. Bundles of one batch are sent
+/// concurrently, by at most `jobs` threads. Publishing stops after the first
+/// batch with errors, because next batches depend on it.
+pub fn publish_in_order(
+    facade: Arc<Mutex<Facade>>,
+    batches: &[Vec<SelfAddressingIdentifier>],
+    jobs: usize,
+    timeout: &Option<u64>,
+    repository_url: &Url,
+) -> Vec<String> {
+    let names: HashMap<String, String> = {
+        let facade_locked = facade.lock().unwrap();
+        facade_locked
+            .fetch_all_refs()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(refn, said)| (said, refn))
+            .collect()
+    };
+    let total: usize = batches.iter().map(Vec::len).sum();
+    let processed = AtomicUsize::new(0);
+
+    for batch in batches {
+        let queue = Mutex::new(batch.iter());
+        let errors = Mutex::new(vec![]);
+        thread::scope(|s| {
+            for _ in 0..jobs.clamp(1, batch.len().max(1)) {
+                s.spawn(|| loop {
+                    let next = queue.lock().unwrap().next();
+                    let said = match next {
+                        Some(said) => said,
+                        None => break,
+                    };
+                    let result = publish_oca_file_for(
+                        facade.clone(),
+                        said.clone(),
+                        timeout,
+                        repository_url.clone(),
+                    );
+                    let count = processed.fetch_add(1, Ordering::SeqCst) + 1;
+                    let name = match names.get(&said.to_string()) {
+                        Some(refn) => format!("{} (name: {})", said, refn),
+                        None => said.to_string(),
+                    };
+                    match result {
+                        Ok(_) => {
+                            println!(
+                                "[{}/{}] Published {} to {}",
+                                count, total, name, repository_url
+                            );
+                        }
+                        Err(e) => {
+                            println!("[{}/{}] Failed to publish {}: {}", count, total, name, e);
+                            errors.lock().unwrap().push(e.to_string());
+                        }
+                    }
+                });
+            }
+        });
+        let errors = errors.into_inner().unwrap();
+        if !errors.is_empty() {
+            let skipped = total - processed.load(Ordering::SeqCst);
+            if skipped > 0 {
+                println!(
+                    "Skipped {} bundles that depend on bundles which failed to publish",
+                    skipped
+                );
+            }
+            return errors;
+        }
+    }
+    vec![]
+}
+
+/// Splits elements into batches, basing on their dependencies. Each element
+/// comes in later batch than all elements it depends on. Elements within a
+/// batch are sorted to keep the order stable.
+pub fn batches<K>(dependencies: &HashMap<K, Vec<K>>) -> Result<Vec<Vec<K>>, GraphError>
+where
+    K: Hash + Eq + Clone + Display,
+{
+    let mut remaining: HashMap<&K, HashSet<&K>> = dependencies
+        .iter()
+        .map(|(key, deps)| {
+            let deps = deps
+                .iter()
+                .filter(|dep| *dep != key && dependencies.contains_key(*dep))
+                .collect();
+            (key, deps)
+        })
+        .collect();
+
+    let mut out = vec![];
+    while !remaining.is_empty() {
+        let mut ready = remaining
+            .iter()
+            .filter(|(_, deps)| deps.is_empty())
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        if ready.is_empty() {
+            return Err(GraphError::Cycle);
+        }
+        ready.sort_by_key(|key| key.to_string());
+        for key in &ready {
+            remaining.remove(*key);
+        }
+        for deps in remaining.values_mut() {
+            for key in &ready {
+                deps.remove(*key);
+            }
+        }
+        out.push(ready.into_iter().cloned().collect());
+    }
+    Ok(out)
+}
+
+#[test]
+fn test_batches() -> anyhow::Result<()> {
+    let dependencies: HashMap<String, Vec<String>> = [
+        ("first", vec![]),
+        ("second", vec![]),
+        ("third", vec!["first", "second"]),
+        ("fourth", vec![]),
+        ("fifth", vec!["third", "fourth"]),
+    ]
+    .into_iter()
+    .map(|(key, deps)| {
+        (
+            key.to_string(),
+            deps.into_iter().map(|dep| dep.to_string()).collect(),
+        )
+    })
+    .collect();
+
+    assert_eq!(
+        batches(&dependencies)?,
+        vec![
+            vec!["first", "fourth", "second"],
+            vec!["third"],
+            vec!["fifth"],
+        ]
+    );
+
+    let mut with_cycle = dependencies.clone();
+    with_cycle.insert("first".to_string(), vec!["fifth".to_string()]);
+    assert!(matches!(batches(&with_cycle), Err(GraphError::Cycle)));
+
+    Ok(())
+}
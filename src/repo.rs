@@ -0,0 +1,206 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::Subcommand;
+use oca_bundle_semantics::state::oca::OCABundle;
+use said::SelfAddressingIdentifier;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    doctor::{build_named, bundle_dependencies, names_by_said, open_store},
+    error::CliError,
+    get_oca_facade,
+    publish::batches,
+};
+
+#[derive(Subcommand)]
+pub enum RepoCommand {
+    /// Write all oca objects of local repository into file, one per line
+    Export {
+        /// Path to output file
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Build oca objects from file written by `oca repo export`
+    Import {
+        /// Path to exported file
+        file: PathBuf,
+    },
+}
+
+/// Single line of exported repository.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RepoEntry {
+    pub said: SelfAddressingIdentifier,
+    /// Dereferenced ocafile, that refers to other bundles only by SAIDs.
+    pub ocafile: String,
+    /// Names (refn) that point to the bundle.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub refs: Vec<String>,
+}
+
+/// Returns all bundles of local repository in order of dependencies. Each
+/// bundle comes after the ones it depends on.
+pub fn export(local_repository_path: &Path) -> Result<Vec<RepoEntry>, CliError> {
+    let (stored, facade) = open_store(local_repository_path)?;
+    let refs = facade.fetch_all_refs().unwrap_or_default();
+
+    let mut dependencies = HashMap::new();
+    for (said, json) in &stored {
+        let bundle: OCABundle = serde_json::from_slice(json).map_err(|e| {
+            CliError::StorageError(format!("OCA bundle {} is invalid: {}", said, e))
+        })?;
+        let bundle_dependencies = bundle_dependencies(&bundle, &refs)
+            .into_iter()
+            .filter(|dependency| stored.contains_key(dependency))
+            .collect::<Vec<_>>();
+        dependencies.insert(said.clone(), bundle_dependencies);
+    }
+
+    let mut names = names_by_said(&refs);
+
+    batches(&dependencies)?
+        .into_iter()
+        .flat_map(|mut batch| {
+            batch.sort();
+            batch
+        })
+        .map(|said| {
+            let refs = names.remove(&said).unwrap_or_default();
+            let said: SelfAddressingIdentifier = said.parse()?;
+            let ocafile = facade
+                .get_oca_bundle_ocafile(said.clone(), true)
+                .map_err(CliError::OcaBundleAstError)?;
+            Ok(RepoEntry {
+                said,
+                ocafile,
+                refs,
+            })
+        })
+        .collect()
+}
+
+/// Builds exported bundles in local repository and checks if their SAIDs
+/// match. Returns number of imported bundles.
+pub fn import(local_repository_path: &Path, entries: &[RepoEntry]) -> Result<usize, CliError> {
+    let mut facade = get_oca_facade(local_repository_path.to_path_buf());
+    for entry in entries {
+        build_named(&mut facade, &entry.said, &entry.ocafile, &entry.refs)?;
+    }
+    Ok(entries.len())
+}
+
+fn save(entries: &[RepoEntry], path: &Path) -> Result<(), CliError> {
+    let file = File::create(path).map_err(CliError::WriteFileFailed)?;
+    let mut writer = BufWriter::new(file);
+    for entry in entries {
+        let line = serde_json::to_string(entry).map_err(CliError::WriteOcaError)?;
+        writeln!(writer, "{}", line).map_err(CliError::WriteFileFailed)?;
+    }
+    writer.flush().map_err(CliError::WriteFileFailed)
+}
+
+fn load(path: &Path) -> Result<Vec<RepoEntry>, CliError> {
+    let file = File::open(path).map_err(|e| CliError::ReadFileFailed(path.to_owned(), e))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|(i, line)| {
+            let line = line.map_err(|e| CliError::ReadFileFailed(path.to_owned(), e))?;
+            serde_json::from_str(&line)
+                .map_err(|e| CliError::RepoImportError(path.to_owned(), i + 1, e.to_string()))
+        })
+        .collect()
+}
+
+pub fn handle_repo_command(
+    command: &RepoCommand,
+    local_repository_path: &Path,
+) -> Result<(), CliError> {
+    match command {
+        RepoCommand::Export { output } => {
+            let entries = export(local_repository_path)?;
+            if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent).map_err(CliError::WriteFileFailed)?;
+            }
+            save(&entries, output)?;
+            println!(
+                "Exported {} OCA bundles to {}",
+                entries.len(),
+                output.display()
+            );
+            Ok(())
+        }
+        RepoCommand::Import { file } => {
+            let entries = load(file)?;
+            let imported = import(local_repository_path, &entries)?;
+            println!("Imported {} OCA bundles from {}", imported, file.display());
+            Ok(())
+        }
+    }
+}
+
+#[test]
+fn test_export_and_import() -> anyhow::Result<()> {
+    let tmp_dir = tempdir::TempDir::new("repo")?;
+    let source = tmp_dir.path().join("source");
+    {
+        let mut facade = get_oca_facade(source.clone());
+        for ocafile in [
+            "-- name=first\nADD ATTRIBUTE name=Text",
+            "-- name=second\nADD ATTRIBUTE first=refn:first",
+            "ADD ATTRIBUTE unnamed=Text",
+        ] {
+            facade.build_from_ocafile(ocafile.to_string()).unwrap();
+        }
+    }
+
+    let entries = export(&source).unwrap();
+    assert_eq!(entries.len(), 3);
+    let position = |refn: &str| {
+        entries
+            .iter()
+            .position(|entry| entry.refs.contains(&refn.to_string()))
+            .unwrap()
+    };
+    assert!(position("first") < position("second"));
+
+    let path = tmp_dir.path().join("repo.jsonl");
+    save(&entries, &path).unwrap();
+    let loaded = load(&path).unwrap();
+    assert_eq!(loaded, entries);
+
+    let target = tmp_dir.path().join("target");
+    assert_eq!(import(&target, &loaded).unwrap(), 3);
+    assert_eq!(export(&target).unwrap(), entries);
+
+    // Tampered line isn't stored and doesn't move its names.
+    let mut tampered = load(&path).unwrap();
+    let second = tampered
+        .iter_mut()
+        .find(|entry| entry.refs.contains(&"second".to_string()))
+        .unwrap();
+    let tampered_ocafile = "ADD ATTRIBUTE other=Text".to_string();
+    second.ocafile = tampered_ocafile.clone();
+    let target = tmp_dir.path().join("tampered");
+    assert!(matches!(
+        import(&target, &tampered),
+        Err(CliError::SaidMismatch { .. })
+    ));
+    let mut facade = get_oca_facade(target);
+    let tampered_said = facade
+        .validate_ocafile(tampered_ocafile)
+        .unwrap()
+        .oca_bundle
+        .said
+        .unwrap();
+    assert!(facade.get_oca_bundle(tampered_said, false).is_err());
+    assert!(!facade.fetch_all_refs().unwrap().contains_key("second"));
+
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{diagnostic::Diagnostic, error::CliError};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ValidateFormat {
+    #[default]
+    Text,
+    Json,
+    Sarif,
+    Junit,
+}
+
+/// Single validation failure, in a form that can be reported by CI tools.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Finding {
+    pub rule_id: &'static str,
+    pub path: Option<PathBuf>,
+    /// Line and column, starting from 1.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+/// Rule ids and their descriptions.
+const RULES: &[(&str, &str)] = &[
+    ("oca/grammar", "Ocafile is not valid"),
+    ("oca/read", "Ocafile can't be read"),
+    ("oca/missing-refn", "Ocafile has no `-- name=` line"),
+    (
+        "oca/graph",
+        "Dependencies between ocafiles can't be resolved",
+    ),
+    ("oca/error", "Validation failed"),
+];
+
+impl Finding {
+    fn from_diagnostic(rule_id: &'static str, diagnostic: Diagnostic) -> Self {
+        Self {
+            rule_id,
+            path: Some(diagnostic.path),
+            line: diagnostic.location.map(|(line, _)| line),
+            column: diagnostic.location.map(|(_, column)| column),
+            message: diagnostic.message,
+            hint: diagnostic.hint,
+        }
+    }
+
+    fn without_location(rule_id: &'static str, path: Option<PathBuf>, message: String) -> Self {
+        Self {
+            rule_id,
+            path,
+            line: None,
+            column: None,
+            message,
+            hint: None,
+        }
+    }
+}
+
+/// Converts errors returned by `validate::validate_directory` into findings.
+pub fn findings(errors: &[CliError]) -> Vec<Finding> {
+    errors
+        .iter()
+        .flat_map(|error| match error {
+            CliError::GrammarError(path, errors) => Diagnostic::from_file(path, errors)
+                .into_iter()
+                .map(|diagnostic| Finding::from_diagnostic("oca/grammar", diagnostic))
+                .collect(),
+            CliError::ReadFileFailed(path, e) => vec![Finding::without_location(
+                "oca/read",
+                Some(path.clone()),
+                e.to_string(),
+            )],
+            CliError::MissingRefn(path) => vec![Finding::without_location(
+                "oca/missing-refn",
+                Some(path.clone()),
+                error.to_string(),
+            )],
+            CliError::GraphError(e) => {
+                vec![Finding::without_location("oca/graph", None, e.to_string())]
+            }
+            e => vec![Finding::without_location("oca/error", None, e.to_string())],
+        })
+        .collect()
+}
+
+/// Returns path relative to current directory, with `/` separators, as
+/// expected by code review tools.
+fn uri(path: &Path) -> String {
+    let relative = env::current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf());
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Returns SARIF 2.1.0 log with one result per finding.
+pub fn to_sarif(findings: &[Finding]) -> Value {
+    let rules = RULES
+        .iter()
+        .map(|(id, description)| json!({"id": id, "shortDescription": {"text": description}}))
+        .collect::<Vec<_>>();
+    let results = findings
+        .iter()
+        .map(|finding| {
+            let mut message = finding.message.clone();
+            if let Some(hint) = &finding.hint {
+                message = format!("{}\nHint: {}", message, hint);
+            }
+            let mut result = json!({
+                "ruleId": finding.rule_id,
+                "level": "error",
+                "message": {"text": message},
+            });
+            if let Some(path) = &finding.path {
+                let mut location = json!({"artifactLocation": {"uri": uri(path)}});
+                if let Some(line) = finding.line {
+                    location["region"] = json!({
+                        "startLine": line,
+                        "startColumn": finding.column.unwrap_or(1),
+                    });
+                }
+                result["locations"] = json!([{ "physicalLocation": location }]);
+            }
+            result
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "oca",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Returns JUnit XML report with one test case per ocafile. Findings not
+/// related to any file are reported in separate test case.
+pub fn to_junit(files: &[PathBuf], findings: &[Finding]) -> String {
+    let mut cases = files
+        .iter()
+        .map(|file| {
+            let failures = findings
+                .iter()
+                .filter(|finding| finding.path.as_ref() == Some(file))
+                .collect::<Vec<_>>();
+            (uri(file), failures)
+        })
+        .collect::<Vec<_>>();
+    let other = findings
+        .iter()
+        .filter(|finding| {
+            finding
+                .path
+                .as_ref()
+                .is_none_or(|path| !files.contains(path))
+        })
+        .collect::<Vec<_>>();
+    if !other.is_empty() {
+        cases.push(("repository".to_string(), other));
+    }
+
+    let failed = cases
+        .iter()
+        .filter(|(_, failures)| !failures.is_empty())
+        .count();
+    let mut out = vec![
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+        format!(
+            r#"<testsuites name="oca validate" tests="{}" failures="{}">"#,
+            cases.len(),
+            failed
+        ),
+        format!(
+            r#"  <testsuite name="oca validate" tests="{}" failures="{}">"#,
+            cases.len(),
+            failed
+        ),
+    ];
+    for (name, failures) in cases {
+        let name = escape_xml(&name);
+        if failures.is_empty() {
+            out.push(format!(
+                r#"    <testcase classname="ocafile" name="{}"/>"#,
+                name
+            ));
+            continue;
+        }
+        out.push(format!(
+            r#"    <testcase classname="ocafile" name="{}">"#,
+            name
+        ));
+        for finding in failures {
+            let position = match (finding.line, finding.column) {
+                (Some(line), Some(column)) => format!("{}:{}: ", line, column),
+                _ => String::new(),
+            };
+            out.push(format!(
+                r#"      <failure type="{}" message="{}">{}{}</failure>"#,
+                finding.rule_id,
+                escape_xml(&finding.message),
+                position,
+                escape_xml(&finding.message)
+            ));
+        }
+        out.push("    </testcase>".to_string());
+    }
+    out.push("  </testsuite>".to_string());
+    out.push("</testsuites>".to_string());
+    out.join("\n")
+}
+
+/// Formats validation result. Returns `None` for text format, which is
+/// printed as errors are found.
+pub fn format_report(
+    format: ValidateFormat,
+    files: &[PathBuf],
+    errors: &[CliError],
+) -> Option<String> {
+    let findings = findings(errors);
+    match format {
+        ValidateFormat::Text => None,
+        ValidateFormat::Json => Some(serde_json::to_string_pretty(&findings).unwrap()),
+        ValidateFormat::Sarif => Some(serde_json::to_string_pretty(&to_sarif(&findings)).unwrap()),
+        ValidateFormat::Junit => Some(to_junit(files, &findings)),
+    }
+}
+
+#[test]
+fn test_reports() {
+    let files = vec![
+        PathBuf::from("person.ocafile"),
+        PathBuf::from("address.ocafile"),
+    ];
+    let findings = vec![
+        Finding {
+            rule_id: "oca/grammar",
+            path: Some(files[0].clone()),
+            line: Some(2),
+            column: Some(20),
+            message: "expected <attr_type>".to_string(),
+            hint: None,
+        },
+        Finding::without_location("oca/graph", None, "Unknown refn: address".to_string()),
+    ];
+
+    let sarif = to_sarif(&findings);
+    assert_eq!(sarif["version"], "2.1.0");
+    let results = sarif["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["ruleId"], "oca/grammar");
+    let location = &results[0]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "person.ocafile");
+    assert_eq!(location["region"]["startLine"], 2);
+    assert!(results[1].get("locations").is_none());
+
+    let junit = to_junit(&files, &findings);
+    assert!(junit.contains(r#"<testsuites name="oca validate" tests="3" failures="2">"#));
+    assert!(junit.contains(r#"<testcase classname="ocafile" name="address.ocafile"/>"#));
+    assert!(junit.contains("expected &lt;attr_type&gt;"));
+    assert!(junit.contains(r#"<testcase classname="ocafile" name="repository">"#));
+}
@@ -0,0 +1,191 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::data::{typed_value, Field, FieldType, Schema};
+
+const WORDS: &[&str] = &[
+    "alpha", "river", "cedar", "orbit", "maple", "harbor", "quartz", "meadow", "falcon", "lumen",
+];
+
+/// Small deterministic generator (SplitMix64), so samples can be reproduced
+/// with the same seed.
+pub struct SampleRng(u64);
+
+impl SampleRng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns number in `min..=max` range.
+    fn range(&mut self, min: u64, max: u64) -> u64 {
+        min + self.next_u64() % (max - min + 1)
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.range(0, items.len() as u64 - 1) as usize]
+    }
+}
+
+/// Returns number of array items allowed by cardinality such as `2`, `1-3`
+/// or `1-`. Arrays without cardinality get 1 to 3 items.
+fn array_length(rng: &mut SampleRng, cardinality: Option<&str>) -> usize {
+    let bound = |value: &str| value.trim().parse::<u64>().ok();
+    let (min, max) = match cardinality {
+        Some(cardinality) => match cardinality.split_once('-') {
+            Some((min, max)) => {
+                let min = bound(min).unwrap_or(0);
+                (min, bound(max).unwrap_or(min + 2))
+            }
+            None => match bound(cardinality) {
+                Some(exact) => (exact, exact),
+                None => (1, 3),
+            },
+        },
+        None => (1, 3),
+    };
+    rng.range(min, max.max(min)) as usize
+}
+
+/// Fills OCA date format, e.g. `YYYY-MM-DD`, with random date.
+fn format_date(rng: &mut SampleRng, format: &str) -> String {
+    [
+        ("YYYY", rng.range(1970, 2030), 4),
+        ("MM", rng.range(1, 12), 2),
+        ("DD", rng.range(1, 28), 2),
+        ("hh", rng.range(0, 23), 2),
+        ("mm", rng.range(0, 59), 2),
+        ("ss", rng.range(0, 59), 2),
+    ]
+    .iter()
+    .fold(format.to_string(), |date, (token, value, width)| {
+        date.replace(token, &format!("{:0width$}", value, width = *width))
+    })
+}
+
+/// Returns candidate values for format given as regular expression. The
+/// first one that matches is used.
+fn text_candidates(rng: &mut SampleRng) -> Vec<String> {
+    let word = rng.pick(WORDS).to_string();
+    let number = rng.range(0, 9999);
+    vec![
+        word.clone(),
+        number.to_string(),
+        format!("{}{}", word, number),
+        word.to_uppercase(),
+        format!("{}@example.com", word),
+        format!("https://example.com/{}", word),
+        format!(
+            "{}-{:02}-{:02}",
+            rng.range(1970, 2030),
+            rng.range(1, 12),
+            rng.range(1, 28)
+        ),
+        format!("{}.{:02}", number, rng.range(0, 99)),
+    ]
+}
+
+fn matching_candidate(rng: &mut SampleRng, format: &str) -> Option<String> {
+    let regex = Regex::new(&format!("^(?:{})$", format)).ok()?;
+    text_candidates(rng)
+        .into_iter()
+        .find(|candidate| regex.is_match(candidate))
+}
+
+fn sample_value(rng: &mut SampleRng, field: &Field, attr_type: &str) -> Value {
+    if let Some(codes) = field.entry_codes.as_ref().filter(|codes| !codes.is_empty()) {
+        return typed_value(rng.pick(codes).as_str(), attr_type);
+    }
+    let format = field.format.as_deref();
+    match attr_type {
+        "Numeric" => {
+            let candidate = format.and_then(|format| matching_candidate(rng, format));
+            candidate
+                .and_then(|candidate| serde_json::from_str(&candidate).ok())
+                .filter(Value::is_number)
+                .unwrap_or_else(|| Value::from(rng.range(0, 100)))
+        }
+        "Boolean" => Value::Bool(rng.range(0, 1) == 1),
+        "DateTime" => Value::String(format_date(rng, format.unwrap_or("YYYY-MM-DDThh:mm:ssZ"))),
+        "Binary" => {
+            let bytes = (0..8).map(|_| rng.range(0, 255) as u8).collect::<Vec<_>>();
+            Value::String(BASE64_STANDARD.encode(bytes))
+        }
+        _ => Value::String(
+            format
+                .and_then(|format| matching_candidate(rng, format))
+                .unwrap_or_else(|| rng.pick(WORDS).to_string()),
+        ),
+    }
+}
+
+fn sample_field(rng: &mut SampleRng, field: &Field, field_type: &FieldType) -> Value {
+    match field_type {
+        FieldType::Array(inner) => {
+            let length = array_length(rng, field.cardinality.as_deref());
+            Value::Array(
+                (0..length)
+                    .map(|_| sample_field(rng, field, inner))
+                    .collect(),
+            )
+        }
+        FieldType::Reference(schema) => sample_record(rng, schema),
+        FieldType::Null => Value::Null,
+        FieldType::Value(attr_type) => sample_value(rng, field, attr_type),
+    }
+}
+
+/// Generates record with value for every attribute of schema.
+pub fn sample_record(rng: &mut SampleRng, schema: &Schema) -> Value {
+    let record = schema
+        .fields
+        .iter()
+        .map(|(name, field)| (name.clone(), sample_field(rng, field, &field.field_type)))
+        .collect::<Map<_, _>>();
+    Value::Object(record)
+}
+
+pub fn sample_records(schema: &Schema, count: usize, seed: u64) -> Vec<Value> {
+    let mut rng = SampleRng::new(seed);
+    (0..count)
+        .map(|_| sample_record(&mut rng, schema))
+        .collect()
+}
+
+#[test]
+fn test_sample_records() -> anyhow::Result<()> {
+    let tmp_dir = tempdir::TempDir::new("sample")?;
+    let mut facade = crate::get_oca_facade(tmp_dir.path().to_path_buf());
+    facade
+        .build_from_ocafile("-- name=address\nADD ATTRIBUTE street=Text".to_string())
+        .unwrap();
+    let ocafile = r#"-- name=person
+ADD ATTRIBUTE name=Text born=DateTime code=Text addresses=Array[refn:address] level=Text
+ADD ATTRIBUTE rating=Numeric
+ADD FORMAT ATTRS born="YYYY-MM-DD" code="[0-9]+"
+ADD ENTRY_CODE ATTRS level=["low", "high"] rating=["1", "2", "3"]
+ADD CARDINALITY ATTRS addresses="2"
+"#;
+    let bundle = facade
+        .validate_ocafile(ocafile.to_string())
+        .unwrap()
+        .oca_bundle;
+    let schema = Schema::from_bundle(&facade, &bundle).unwrap();
+
+    let records = sample_records(&schema, 5, 42);
+    assert_eq!(records, sample_records(&schema, 5, 42));
+    assert_ne!(records, sample_records(&schema, 5, 7));
+    assert!(crate::data::validate_records(&schema, &records).is_empty());
+    assert_eq!(records[0]["addresses"].as_array().unwrap().len(), 2);
+    assert!(records.iter().all(|record| record["rating"].is_number()));
+
+    Ok(())
+}
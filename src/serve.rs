@@ -0,0 +1,303 @@
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    net::ToSocketAddrs,
+    sync::{Arc, Mutex},
+};
+
+use oca_rs::{facade::bundle::BundleElement, Facade};
+use said::SelfAddressingIdentifier;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+use url::Url;
+
+use crate::error::{BuildingFailures, CliError};
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+/// Minimal HTTP server compatible with OCA repository `/oca-bundles`
+/// endpoints. It is backed by local repository, so it can be used instead of
+/// online repository in tests and demos.
+pub struct RepositoryServer {
+    server: Server,
+    facade: Arc<Mutex<Facade>>,
+    /// Published presentations, by SAID. They are kept only in memory.
+    presentations: HashMap<String, Value>,
+}
+
+impl RepositoryServer {
+    pub fn bind(addr: impl ToSocketAddrs, facade: Arc<Mutex<Facade>>) -> Result<Self, CliError> {
+        let server = Server::http(addr).map_err(|e| CliError::ServeError(e.to_string()))?;
+        Ok(Self {
+            server,
+            facade,
+            presentations: HashMap::new(),
+        })
+    }
+
+    /// Returns url of running server, that can be used as `repository_url`.
+    pub fn url(&self) -> Option<Url> {
+        self.server
+            .server_addr()
+            .to_ip()
+            .and_then(|addr| Url::parse(&format!("http://{}/", addr)).ok())
+    }
+
+    /// Handles incoming requests until the server socket is closed.
+    pub fn run(mut self) {
+        while let Ok(request) = self.server.recv() {
+            if let Err(e) = self.handle(request) {
+                error!("Failed to respond: {}", e);
+            }
+        }
+    }
+
+    fn handle(&mut self, mut request: Request) -> std::io::Result<()> {
+        let method = request.method().clone();
+        let url = request.url().to_string();
+        info!("{} {}", method, url);
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+
+        let response = match (method, segments.as_slice()) {
+            (Method::Post, ["oca-bundles"]) => {
+                let mut ocafile = String::new();
+                request.as_reader().read_to_string(&mut ocafile)?;
+                self.add_oca_file(ocafile)
+            }
+            (Method::Get, ["oca-bundles"]) => self.list(query),
+            (Method::Get, ["oca-bundles", said]) => self.oca_bundle(said, query),
+            (Method::Get, ["oca-bundles", said, "ocafile"]) => self.ocafile(said),
+            (Method::Post, ["presentations"]) => {
+                let mut presentation = String::new();
+                request.as_reader().read_to_string(&mut presentation)?;
+                self.add_presentation(presentation)
+            }
+            (Method::Get, ["presentations", said]) => match self.presentations.get(*said) {
+                Some(presentation) => json_response(200, presentation),
+                None => not_found(said),
+            },
+            _ => json_response(404, &json!({"errors": ["Not found"]})),
+        };
+        request.respond(response)
+    }
+
+    fn add_oca_file(&self, ocafile: String) -> HttpResponse {
+        let mut facade = self.facade.lock().unwrap();
+        match facade.build_from_ocafile(ocafile) {
+            Ok(BundleElement::Mechanics(oca_bundle)) => match oca_bundle.said {
+                Some(said) => {
+                    json_response(200, &json!({"success": true, "said": said.to_string()}))
+                }
+                None => missing_said(),
+            },
+            Ok(BundleElement::Transformation(_)) => json_response(
+                400,
+                &json!({"success": false, "errors": ["Transformation files are not supported"]}),
+            ),
+            Err(e) => json_response(
+                400,
+                &json!({"success": false, "errors": [BuildingFailures::from(e).to_string()]}),
+            ),
+        }
+    }
+
+    fn list(&self, query: &str) -> HttpResponse {
+        let params = query_params(query);
+        let page = params
+            .get("page")
+            .and_then(|page| page.parse().ok())
+            .unwrap_or(1);
+        let size = params
+            .get("size")
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(20);
+        let facade = self.facade.lock().unwrap();
+        let refs = facade.fetch_all_refs().unwrap_or_default();
+        match facade.fetch_all_oca_bundle(size, page) {
+            Ok(result) => {
+                let records = result
+                    .records
+                    .into_iter()
+                    .map(|bundle| {
+                        let said = bundle.said?.to_string();
+                        let name = refs
+                            .iter()
+                            .find(|(_, v)| **v == said)
+                            .map(|(refn, _)| refn.clone());
+                        Some(json!({"said": said, "name": name}))
+                    })
+                    .collect::<Option<Vec<_>>>();
+                let Some(records) = records else {
+                    return missing_said();
+                };
+                json_response(
+                    200,
+                    &json!({"records": records, "metadata": {"total": result.metadata.total, "page": page}}),
+                )
+            }
+            Err(errors) => json_response(500, &json!({ "errors": errors })),
+        }
+    }
+
+    fn oca_bundle(&self, said: &str, query: &str) -> HttpResponse {
+        let said = match said.parse::<SelfAddressingIdentifier>() {
+            Ok(said) => said,
+            Err(e) => return json_response(400, &json!({"errors": [e.to_string()]})),
+        };
+        let with_dependencies = query_params(query)
+            .get("w")
+            .map(|w| *w == "true")
+            .unwrap_or(false);
+        let facade = self.facade.lock().unwrap();
+        match facade.get_oca_bundle(said.clone(), with_dependencies) {
+            Ok(bundles) if with_dependencies => json_response(200, &json!(bundles)),
+            Ok(bundles) => json_response(200, &json!(bundles.bundle)),
+            Err(_) => not_found(&said.to_string()),
+        }
+    }
+
+    fn ocafile(&self, said: &str) -> HttpResponse {
+        let said = match said.parse::<SelfAddressingIdentifier>() {
+            Ok(said) => said,
+            Err(e) => return json_response(400, &json!({"errors": [e.to_string()]})),
+        };
+        let facade = self.facade.lock().unwrap();
+        match facade.get_oca_bundle_ocafile(said.clone(), false) {
+            Ok(ocafile) => Response::from_string(ocafile),
+            Err(_) => not_found(&said.to_string()),
+        }
+    }
+
+    fn add_presentation(&mut self, presentation: String) -> HttpResponse {
+        let presentation: Value = match serde_json::from_str(&presentation) {
+            Ok(presentation) => presentation,
+            Err(e) => return json_response(400, &json!({"errors": [e.to_string()]})),
+        };
+        match presentation.get("d").and_then(Value::as_str) {
+            Some(said) => {
+                let said = said.to_string();
+                self.presentations.insert(said.clone(), presentation);
+                json_response(200, &json!({"success": true, "said": said}))
+            }
+            None => json_response(
+                400,
+                &json!({"success": false, "errors": ["Missing presentation SAID"]}),
+            ),
+        }
+    }
+}
+
+fn query_params(query: &str) -> HashMap<&str, &str> {
+    query
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .collect()
+}
+
+fn not_found(said: &str) -> HttpResponse {
+    json_response(404, &json!({"errors": [format!("Not found: {}", said)]}))
+}
+
+fn missing_said() -> HttpResponse {
+    json_response(500, &json!({"errors": ["Stored OCA bundle has no SAID"]}))
+}
+
+fn json_response(status: u16, body: &Value) -> HttpResponse {
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    Response::from_data(body.to_string())
+        .with_status_code(status)
+        .with_header(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    use crate::{
+        build::{build, handle_publish},
+        cache::BuiltOCACache,
+        get_oca_facade,
+        serve::RepositoryServer,
+        utils::{exists_in_repo, get_from_repo, load_nodes, send_to_repo},
+    };
+
+    #[test]
+    fn test_publish_and_fetch() {
+        let tmp_dir = tempdir::TempDir::new("serve").unwrap();
+        let facade = Arc::new(Mutex::new(get_oca_facade(tmp_dir.path().to_path_buf())));
+        let server = RepositoryServer::bind("127.0.0.1:0", facade).unwrap();
+        let url = server.url().unwrap();
+        thread::spawn(move || server.run());
+
+        let ocafile = "ADD ATTRIBUTE name=Text number=Numeric".to_string();
+        send_to_repo(&url, ocafile, 5).unwrap();
+
+        let list: serde_json::Value = reqwest::blocking::get(url.join("oca-bundles").unwrap())
+            .unwrap()
+            .json()
+            .unwrap();
+        assert_eq!(list["metadata"]["total"], 1);
+        let said = list["records"][0]["said"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        assert!(exists_in_repo(&url, &said, 5).unwrap());
+        let fetched = get_from_repo(&url, &said, 5).unwrap();
+        assert!(fetched.contains("name=Text"));
+
+        let unknown = "EKrgT8vjEMrFLp7JbrFIub2e3q3O1AL43uBeUellrXRz"
+            .parse()
+            .unwrap();
+        assert!(!exists_in_repo(&url, &unknown, 5).unwrap());
+    }
+
+    #[test]
+    fn test_handle_publish() {
+        let remote_dir = tempdir::TempDir::new("serve_remote").unwrap();
+        let remote_facade = Arc::new(Mutex::new(get_oca_facade(remote_dir.path().to_path_buf())));
+        let server = RepositoryServer::bind("127.0.0.1:0", remote_facade).unwrap();
+        let url = server.url().unwrap();
+        thread::spawn(move || server.run());
+
+        let tmp_dir = tempdir::TempDir::new("serve_local").unwrap();
+        let ocafiles_dir = tmp_dir.path().join("ocafiles");
+        fs::create_dir_all(&ocafiles_dir).unwrap();
+        fs::write(
+            ocafiles_dir.join("first.ocafile"),
+            "-- name=first\nADD ATTRIBUTE name=Text",
+        )
+        .unwrap();
+        fs::write(
+            ocafiles_dir.join("second.ocafile"),
+            "-- name=second\nADD ATTRIBUTE first=refn:first",
+        )
+        .unwrap();
+
+        let nodes = load_nodes(None, Some(&ocafiles_dir)).unwrap();
+        let facade = Arc::new(Mutex::new(get_oca_facade(
+            tmp_dir.path().join("repository"),
+        )));
+        let cache = BuiltOCACache::new(tmp_dir.path().join("cache")).unwrap();
+        let saids = nodes
+            .iter()
+            .map(|node| {
+                build(facade.clone(), node, Some(&cache), None)
+                    .unwrap()
+                    .unwrap()
+                    .0
+            })
+            .collect::<Vec<_>>();
+
+        handle_publish(facade, url.clone(), &nodes, &cache).unwrap();
+        for said in &saids {
+            assert!(exists_in_repo(&url, said, 5).unwrap());
+        }
+    }
+}
@@ -27,7 +27,9 @@ use url::Url;
 use crate::{
     dependency_graph::{parse_name, DependencyGraph, MutableGraph, Node, NodeParsingError},
     error::CliError,
-    publish_oca_file_for, saids_to_publish,
+    history::RefnHistory,
+    publish::publish_batches,
+    publish_oca_file_for,
     tui::{details::Details, get_oca_bundle_by_said, output_window::message_list::Message},
     utils::{handle_panic, parse_url},
     validate::build,
@@ -53,6 +55,8 @@ pub enum AppError {
     UnknownRemoteRepoUrl,
     #[error("Remote repository url parse error: {0}")]
     WrongUrl(#[from] url::ParseError),
+    #[error("Unknown remote: {0}. Check `remotes` in config file.")]
+    UnknownRemote(String),
 }
 pub struct App {
     bundles: BundleList,
@@ -61,10 +65,14 @@ pub struct App {
     graph: MutableGraph,
     active_window: Window,
     base: PathBuf,
-    remote_repository: Option<String>,
+    /// Remote repositories as `(name, url)` pairs.
+    remotes: Vec<(String, String)>,
+    /// Index of remote used for publishing.
+    current_remote: Option<usize>,
     changes: ChangesWindow,
     details: DetailsWindow,
     publish_timeout: Option<u64>,
+    history: Option<RefnHistory>,
 }
 
 enum Window {
@@ -75,14 +83,17 @@ enum Window {
 }
 
 impl App {
+    #[allow(clippy::too_many_arguments)]
     pub fn new<I: IntoIterator<Item = Result<Node, NodeParsingError>> + Clone>(
         base: PathBuf,
         to_show: I,
         facade: Arc<Mutex<Facade>>,
         paths: Vec<PathBuf>,
         size: usize,
-        remote_repo_url: Option<String>,
+        remotes: Vec<(String, String)>,
+        selected_remote: Option<String>,
         publish_timeout: Option<u64>,
+        history: Option<RefnHistory>,
     ) -> Result<App, AppError> {
         let graph = match DependencyGraph::from_paths(&paths) {
             Ok(graph) => Ok(Arc::new(graph)),
@@ -95,6 +106,16 @@ impl App {
         App::setup_panic_hooks()?;
         let changes = ChangesWindow::new(&base, mut_graph.clone());
         let details = DetailsWindow::new();
+        let current_remote = match selected_remote {
+            Some(name) => Some(
+                remotes
+                    .iter()
+                    .position(|(remote_name, _)| remote_name.eq(&name))
+                    .ok_or(AppError::UnknownRemote(name))?,
+            ),
+            None if remotes.is_empty() => None,
+            None => Some(0),
+        };
 
         Ok(App {
             bundles: list,
@@ -103,10 +124,12 @@ impl App {
             graph: mut_graph,
             facade,
             base,
-            remote_repository: remote_repo_url,
+            remotes,
+            current_remote,
             changes,
             publish_timeout,
             details,
+            history,
         })
     }
 }
@@ -133,6 +156,28 @@ impl App {
         true
     }
 
+    /// Switch publishing target to the next configured remote repository.
+    fn change_remote(&mut self) -> bool {
+        if self.remotes.is_empty() {
+            let errs = self.output.error_list_mut();
+            let mut errs = errs.lock().unwrap();
+            errs.append(Message::Error(CliError::UnknownRemoteRepoUrl));
+            return true;
+        }
+        let next = self
+            .current_remote
+            .map_or(0, |current| (current + 1) % self.remotes.len());
+        self.current_remote = Some(next);
+        let (name, url) = &self.remotes[next];
+        let errs = self.output.error_list_mut();
+        let mut errs = errs.lock().unwrap();
+        errs.append(Message::Info(format!(
+            "Publishing target changed to {} ({})",
+            name, url
+        )));
+        true
+    }
+
     fn handle_input(&mut self) -> bool {
         let output = if let Window::Help = self.active_window {
             match event::read() {
@@ -211,6 +256,7 @@ impl App {
                             self.output.set_currently_validated(paths);
                             self.handle_publish(selected, self.facade.clone())
                         }
+                        KeyCode::Char('r') => Ok(self.change_remote()),
                         KeyCode::Tab => Ok(self.change_window()),
                         KeyCode::F(1) => {
                             self.active_window = Window::Help;
@@ -279,6 +325,7 @@ impl App {
         let list = self.bundles.items.clone();
         let to_show_dir = Arc::new(self.base.clone());
         let changes = self.changes.changes();
+        let history = self.history.clone();
 
         thread::spawn(move || {
             let start = Instant::now();
@@ -309,6 +356,7 @@ impl App {
                             &mut graph,
                             errs.clone(),
                             &cache,
+                            history.as_ref(),
                         ) {
                             Ok(mut cached) => {
                                 cache.append(&mut cached);
@@ -356,10 +404,10 @@ impl App {
         let current_path = self.output.current_path();
         let errs = self.output.error_list_mut();
         let remote_repository: Url = parse_url(
-            self.remote_repository
-                .as_ref()
-                .ok_or(CliError::UnknownRemoteRepoUrl)?
-                .clone(),
+            self.current_remote
+                .and_then(|i| self.remotes.get(i))
+                .map(|(_name, url)| url.clone())
+                .ok_or(CliError::UnknownRemoteRepoUrl)?,
         )?;
         self.output.mark_publish();
         let timeout = self.publish_timeout;
@@ -384,8 +432,14 @@ impl App {
                 .collect();
             match saids {
                 Ok(saids) => {
-                    // Find dependant saids for said. Returns set of unique saids that need to be published.
-                    let saids_to_publish = saids_to_publish(facade.clone(), &saids);
+                    // Find dependant saids for said, in order of publishing.
+                    let saids_to_publish = match publish_batches(facade.clone(), &saids) {
+                        Ok(batches) => batches.into_iter().flatten().collect::<Vec<_>>(),
+                        Err(e) => {
+                            update_errors(errs.clone(), vec![e], &current_path);
+                            return;
+                        }
+                    };
                     // Make post request for all saids
                     let unwind_res = std::panic::catch_unwind(AssertUnwindSafe(|| {
                         saids_to_publish
@@ -523,7 +577,11 @@ impl Widget for &mut App {
             let horizontal = Layout::horizontal([Constraint::Percentage(70), Constraint::Min(0)]);
             let [list_area, details_area] = horizontal.areas(list_area);
 
-            self.render_title(header_area, buf, "OCA tool");
+            let title = match self.current_remote.and_then(|i| self.remotes.get(i)) {
+                Some((name, _url)) => format!("OCA tool (remote: {})", name),
+                None => "OCA tool".to_string(),
+            };
+            self.render_title(header_area, buf, &title);
             self.bundles.render(list_area, buf);
             self.output.render(output_area, buf);
             // self.changes.render(changes_area, buf);
@@ -562,6 +620,7 @@ impl App {
             ("v", "validate selected OCA files"),
             ("b", "build selected OCA files"),
             ("p", "publish selected OCA files"),
+            ("r", "switch remote repository used for publishing"),
             ("F1", "Open help"),
         ];
 
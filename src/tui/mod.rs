@@ -17,6 +17,7 @@ use std::{
 use crate::{
     dependency_graph::{Node, NodeParsingError},
     error::CliError,
+    history::RefnHistory,
 };
 
 use self::app::AppError;
@@ -30,13 +31,16 @@ mod item;
 pub(crate) mod logging;
 pub mod output_window;
 
+#[allow(clippy::too_many_arguments)]
 pub fn draw<I>(
     base_dir: PathBuf,
     nodes_to_show: I,
     paths: Vec<PathBuf>,
     facade: Arc<Mutex<Facade>>,
-    repository_url: Option<String>,
+    remotes: Vec<(String, String)>,
+    selected_remote: Option<String>,
     publish_timeout: Option<u64>,
+    history: Option<RefnHistory>,
 ) -> Result<(), AppError>
 where
     I: IntoIterator<Item = Result<Node, NodeParsingError>> + Clone,
@@ -53,8 +57,10 @@ where
         facade,
         paths,
         size as usize,
-        repository_url,
+        remotes,
+        selected_remote,
         publish_timeout,
+        history,
     )?
     .run(terminal);
 
@@ -10,7 +10,7 @@ use ratatui::{
 };
 use tui_widget_list::ListableWidget;
 
-use crate::error::CliError;
+use crate::{diagnostic::Diagnostic, error::CliError};
 
 #[derive(Debug)]
 pub enum Message {
@@ -117,72 +117,61 @@ impl MessageList {
     }
 }
 
-pub struct MessageLine<'a>(Line<'a>, usize, Style);
+pub struct MessageLine<'a>(Text<'a>, usize, Style);
+
+/// Renders diagnostics as lines, with message highlighted.
+fn diagnostic_lines(kind: &str, diagnostics: Vec<Diagnostic>) -> Vec<Line<'static>> {
+    diagnostics
+        .into_iter()
+        .flat_map(|diagnostic| {
+            let mut lines = diagnostic.lines().into_iter();
+            let message = lines.next().unwrap_or_default();
+            let message = message.strip_prefix("error: ").unwrap_or(&message);
+            let header = Line::from(vec![
+                Span::styled(
+                    format!("! {} error: ", kind),
+                    Style::default()
+                        .fg(Color::Red)
+                        .add_modifier(Modifier::ITALIC),
+                ),
+                Span::styled(
+                    message.to_string(),
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ),
+            ]);
+            std::iter::once(header).chain(lines.map(|line| {
+                let style = if line.trim_end().ends_with('^') {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default().fg(Color::Blue)
+                };
+                Line::styled(line, style)
+            }))
+        })
+        .collect()
+}
 
 impl<'a> MessageLine<'a> {
     pub fn new(er: &'a Message, size: usize) -> Self {
-        let line = match er {
-            Message::Error(CliError::GrammarError(file, errors)) => errors
-                .iter()
-                .flat_map(|err| {
-                    vec![
-                        Span::styled(
-                            "! Validation error in file ".to_string(),
-                            Style::default()
-                                .fg(Color::Red)
-                                .add_modifier(Modifier::ITALIC),
-                        ),
-                        Span::styled(
-                            format!("{}:", file.to_str().unwrap()),
-                            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-                        ),
-                        Span::styled(
-                            format!(" {}", err),
-                            Style::default()
-                                .fg(Color::Red)
-                                .add_modifier(Modifier::ITALIC),
-                        ),
-                    ]
-                })
-                .collect::<Vec<_>>(),
-            Message::Error(CliError::BuildingError(file, errors)) => errors
-                .0
-                .iter()
-                .flat_map(|err| match err {
-                    oca_rs::facade::build::Error::ValidationError(ve) => {
-                        ve.iter().map(move |atomic_error| {
-                            vec![
-                                Span::styled(
-                                    "! Building error in file ".to_string(),
-                                    Style::default()
-                                        .fg(Color::Red)
-                                        .add_modifier(Modifier::ITALIC),
-                                ),
-                                Span::styled(
-                                    format!("{}:", file.to_str().unwrap()),
-                                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-                                ),
-                                Span::styled(
-                                    format!(" {}\n", atomic_error),
-                                    Style::default()
-                                        .fg(Color::Red)
-                                        .add_modifier(Modifier::ITALIC),
-                                ),
-                            ]
-                        })
-                    }
-                })
-                .flatten()
-                .collect(),
-            Message::Error(e) => vec![Span::styled(e.to_string(), Style::default())],
-            Message::Info(info) => vec![Span::styled(info, Style::default().fg(Color::Green))],
+        let lines = match er {
+            Message::Error(CliError::GrammarError(file, errors)) => {
+                diagnostic_lines("Validation", Diagnostic::from_file(file, errors))
+            }
+            Message::Error(CliError::BuildingError(file, errors)) => diagnostic_lines(
+                "Building",
+                Diagnostic::from_file(file, errors.validation_errors()),
+            ),
+            Message::Error(e) => vec![Line::styled(e.to_string(), Style::default())],
+            Message::Info(info) => vec![Line::styled(
+                info.clone(),
+                Style::default().fg(Color::Green),
+            )],
         };
-        let height = line.iter().map(|l| l.content.len()).sum::<usize>() as f32 / size as f32;
-        Self(
-            Line::from(line).style(Style::default()),
-            height.ceil() as usize,
-            Style::default(),
-        )
+        let height = lines
+            .iter()
+            .map(|line| (line.width() as f32 / size as f32).ceil().max(1.) as usize)
+            .sum();
+        Self(Text::from(lines), height, Style::default())
     }
 }
 
@@ -191,8 +180,10 @@ impl<'a> Widget for MessageLine<'a> {
     where
         Self: Sized,
     {
-        let l = Text::from(self.0.clone());
-        let par = Paragraph::new(l).wrap(Wrap { trim: true }).style(self.2);
+        // Don't trim, so carets stay under the code they point to.
+        let par = Paragraph::new(self.0)
+            .wrap(Wrap { trim: false })
+            .style(self.2);
         par.render(area, buf)
     }
 }
@@ -1,18 +1,100 @@
 use std::{
     any::Any,
+    collections::HashMap,
     fs,
     path::{Path, PathBuf},
 };
 
+use oca_ast_semantics::ast::{NestedAttrType, RefValue};
+use oca_rs::Facade;
+use regex::Regex;
 use said::SelfAddressingIdentifier;
 use url::Url;
 use walkdir::WalkDir;
 
 use crate::{
+    config::{Config, DEFAULT_REMOTE_NAME},
     dependency_graph::{parse_node, GraphError, MutableGraph, Node},
     error::CliError,
+    history::{HistoryError, RefnHistory},
 };
 
+/// Selects OCA bundle by SAID or by name (refn).
+#[derive(clap::Args, Debug)]
+#[group(required = true, multiple = false)]
+pub struct BundleSelector {
+    /// SAID of OCA bundle. Name (refn) is accepted as well
+    #[arg(short, long)]
+    pub said: Option<String>,
+    /// Name (refn) of OCA bundle, optionally with version tag, e.g.
+    /// `person@v1.2.0`
+    #[arg(short, long)]
+    pub refn: Option<String>,
+}
+
+impl BundleSelector {
+    pub fn resolve(
+        &self,
+        facade: &Facade,
+        history: Option<&RefnHistory>,
+    ) -> Result<SelfAddressingIdentifier, CliError> {
+        match (&self.said, &self.refn) {
+            (Some(said), _) => resolve_said(facade, history, said),
+            (None, Some(refn)) => resolve_refn(facade, history, refn),
+            (None, None) => Err(CliError::OCABundleRefnNotFound(String::new())),
+        }
+    }
+
+    /// Returns name (refn) of selected OCA bundle, if it has one.
+    pub fn refn(&self, facade: &Facade) -> Option<String> {
+        let strip_tag = |refn: &str| refn.split('@').next().unwrap_or(refn).to_string();
+        match (&self.said, &self.refn) {
+            (_, Some(refn)) => Some(strip_tag(refn)),
+            (Some(said), None) if said.parse::<SelfAddressingIdentifier>().is_err() => {
+                Some(strip_tag(said))
+            }
+            (Some(said), None) => facade
+                .fetch_all_refs()
+                .unwrap_or_default()
+                .into_iter()
+                .find(|(_, v)| v == said)
+                .map(|(refn, _)| refn),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Parses SAID, or finds SAID that given name (refn) points to. Name can be
+/// followed by version tag, e.g. `person@v1.2.0`.
+pub fn resolve_said(
+    facade: &Facade,
+    history: Option<&RefnHistory>,
+    said_or_refn: &str,
+) -> Result<SelfAddressingIdentifier, CliError> {
+    match said_or_refn.parse() {
+        Ok(said) => Ok(said),
+        Err(_) => resolve_refn(facade, history, said_or_refn),
+    }
+}
+
+pub fn resolve_refn(
+    facade: &Facade,
+    history: Option<&RefnHistory>,
+    refn: &str,
+) -> Result<SelfAddressingIdentifier, CliError> {
+    if let Some((refn, version)) = refn.split_once('@') {
+        return match history {
+            Some(history) => Ok(history.tagged(refn, version)?),
+            None => Err(HistoryError::UnknownTag(refn.to_string(), version.to_string()).into()),
+        };
+    }
+    let refs = facade.fetch_all_refs().unwrap_or_default();
+    let said = refs
+        .get(refn)
+        .ok_or(CliError::OCABundleRefnNotFound(refn.to_string()))?;
+    Ok(said.parse()?)
+}
+
 pub fn load_ocafiles_all(
     file_path: Option<&PathBuf>,
     dir_path: Option<&PathBuf>,
@@ -144,25 +226,61 @@ pub fn handle_panic(panic: Box<dyn Any + Send>) -> CliError {
     err
 }
 
+/// Finds url of remote repository. Explicitly provided url has the highest
+/// priority, then remote of given name. If none of them is provided, the
+/// default remote from config file is used.
 pub fn load_remote_repo_url(
     repository_url: &Option<String>,
-    remote_repo_url_from_config: Option<String>,
+    remote: &Option<String>,
+    config: &Config,
 ) -> Result<Url, CliError> {
-    match (repository_url, remote_repo_url_from_config) {
-        (None, None) => Err(CliError::UnknownRemoteRepoUrl),
-        (None, Some(config_url)) => parse_url(config_url),
+    match (repository_url, remote) {
         (Some(repo_url), _) => parse_url(repo_url.clone()),
+        (None, Some(name)) => remote_url(name, config),
+        (None, None) => match config.default_remote_name() {
+            Some(name) => remote_url(&name, config),
+            None => Err(CliError::UnknownRemoteRepoUrl),
+        },
+    }
+}
+
+fn remote_url(name: &str, config: &Config) -> Result<Url, CliError> {
+    match (config.remotes.get(name), &config.repository_url) {
+        (Some(remote), _) => parse_url(remote.url.clone()),
+        (None, Some(url)) if name == DEFAULT_REMOTE_NAME => parse_url(url.clone()),
+        (None, _) => Err(CliError::UnknownRemote(name.to_string())),
     }
 }
 
+/// Timeout of requests to remote repository, in seconds, used if none is
+/// given.
+pub const DEFAULT_TIMEOUT: u64 = 666;
+
 pub fn send_to_repo(repository_url: &Url, ocafile: String, timeout: u64) -> Result<(), CliError> {
+    post_to_repo(repository_url, "oca-bundles", ocafile, None, timeout)
+}
+
+/// Sends payload to given endpoint of remote repository.
+pub fn post_to_repo(
+    repository_url: &Url,
+    endpoint: &str,
+    payload: String,
+    content_type: Option<&str>,
+    timeout: u64,
+) -> Result<(), CliError> {
     let client = reqwest::blocking::Client::builder()
         .timeout(std::time::Duration::from_secs(timeout))
         .build()
         .expect("Failed to create reqwest client");
-    let url = repository_url.join("oca-bundles")?;
-    info!("Publish OCA bundle to: {} with payload: {}", url, ocafile);
-    match client.post(url).body(ocafile).send() {
+    let url = repository_url.join(endpoint)?;
+    info!("Publish to: {} with payload: {}", url, payload);
+    let request = match content_type {
+        Some(content_type) => client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, content_type),
+        None => client.post(url),
+    };
+    match request.body(payload).send() {
         Ok(v) => match v.error_for_status() {
             Ok(v) => {
                 info!("{},{}", v.status(), v.text().unwrap());
@@ -177,7 +295,7 @@ pub fn send_to_repo(repository_url: &Url, ocafile: String, timeout: u64) -> Resu
             }
         },
         Err(e) => {
-            info!("Error while uploading OCAFILE: {}", e);
+            info!("Error while uploading: {}", e);
             Err(CliError::PublishError(
                 SelfAddressingIdentifier::default(),
                 vec![format!("Sending error: {}", e)],
@@ -185,3 +303,146 @@ pub fn send_to_repo(repository_url: &Url, ocafile: String, timeout: u64) -> Resu
         }
     }
 }
+
+/// Checks if OCA bundle of given SAID is available in remote repository. Only
+/// `404 Not Found` means that it isn't, other failures are errors.
+pub fn exists_in_repo(
+    repository_url: &Url,
+    said: &SelfAddressingIdentifier,
+    timeout: u64,
+) -> Result<bool, CliError> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(timeout))
+        .build()
+        .expect("Failed to create reqwest client");
+    let url = repository_url.join(&format!("oca-bundles/{}", said))?;
+    info!("Check OCA bundle in: {}", url);
+    match client.get(url).send() {
+        Ok(v) if v.status() == reqwest::StatusCode::NOT_FOUND => Ok(false),
+        Ok(v) => v
+            .error_for_status()
+            .map(|_| true)
+            .map_err(|e| CliError::FetchError(said.clone(), vec![e.to_string()])),
+        Err(e) => Err(CliError::FetchError(
+            said.clone(),
+            vec![format!("Sending error: {}", e)],
+        )),
+    }
+}
+
+/// Downloads ocafile of OCA bundle of given SAID from remote repository.
+pub fn get_from_repo(
+    repository_url: &Url,
+    said: &SelfAddressingIdentifier,
+    timeout: u64,
+) -> Result<String, CliError> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(timeout))
+        .build()
+        .expect("Failed to create reqwest client");
+    let url = repository_url.join(&format!("oca-bundles/{}/ocafile", said))?;
+    info!("Fetch OCA bundle from: {}", url);
+    match client.get(url).send() {
+        Ok(v) => match v.error_for_status() {
+            Ok(v) => v
+                .text()
+                .map_err(|e| CliError::FetchError(said.clone(), vec![e.to_string()])),
+            Err(er) => {
+                info!("error: {:?}", er);
+                Err(CliError::FetchError(said.clone(), vec![er.to_string()]))
+            }
+        },
+        Err(e) => {
+            info!("Error while downloading OCAFILE: {}", e);
+            Err(CliError::FetchError(
+                said.clone(),
+                vec![format!("Sending error: {}", e)],
+            ))
+        }
+    }
+}
+
+/// Returns reference of attribute, also one nested in arrays.
+pub(crate) fn attribute_reference(attr: &NestedAttrType) -> Option<&RefValue> {
+    match attr {
+        NestedAttrType::Reference(reference) => Some(reference),
+        NestedAttrType::Array(inner) => attribute_reference(inner),
+        NestedAttrType::Value(_) | NestedAttrType::Null => None,
+    }
+}
+
+/// Returns SAID of OCA bundle referenced by attribute, if any. References by
+/// name are resolved with `refs`.
+pub(crate) fn reference_said(
+    attr: &NestedAttrType,
+    refs: &HashMap<String, String>,
+) -> Option<SelfAddressingIdentifier> {
+    match attribute_reference(attr)? {
+        RefValue::Said(said) => Some(said.clone()),
+        RefValue::Name(refn) => refs.get(refn).and_then(|said| said.parse().ok()),
+    }
+}
+
+/// Returns SAIDs of OCA bundles referenced in ocafile by `refs:` keyword.
+pub fn referenced_saids(ocafile: &str) -> Vec<SelfAddressingIdentifier> {
+    let re = Regex::new(r"refs:([A-Za-z0-9_-]+)").expect("Invalid regex");
+    re.captures_iter(ocafile)
+        .filter_map(|cap| cap.get(1).and_then(|said| said.as_str().parse().ok()))
+        .collect()
+}
+
+#[test]
+fn test_load_remote_repo_url() -> anyhow::Result<()> {
+    use crate::config::Remote;
+
+    let mut config = Config::new(PathBuf::from("/tmp/.oca"));
+    assert!(matches!(
+        load_remote_repo_url(&None, &None, &config),
+        Err(CliError::UnknownRemoteRepoUrl)
+    ));
+
+    config.repository_url = Some("https://default.example.com".to_string());
+    config.remotes.insert(
+        "staging".to_string(),
+        Remote {
+            url: "https://staging.example.com/".to_string(),
+        },
+    );
+    assert_eq!(
+        load_remote_repo_url(&None, &None, &config)?.as_str(),
+        "https://default.example.com/"
+    );
+    assert_eq!(
+        load_remote_repo_url(&None, &Some("staging".to_string()), &config)?.as_str(),
+        "https://staging.example.com/"
+    );
+    assert_eq!(
+        load_remote_repo_url(
+            &Some("https://explicit.example.com".to_string()),
+            &Some("staging".to_string()),
+            &config
+        )?
+        .as_str(),
+        "https://explicit.example.com/"
+    );
+    assert!(matches!(
+        load_remote_repo_url(&None, &Some("prod".to_string()), &config),
+        Err(CliError::UnknownRemote(_))
+    ));
+
+    config.default_remote = Some("staging".to_string());
+    assert_eq!(
+        load_remote_repo_url(&None, &None, &config)?.as_str(),
+        "https://staging.example.com/"
+    );
+    assert_eq!(
+        config
+            .remotes()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>(),
+        vec!["default", "staging"]
+    );
+
+    Ok(())
+}
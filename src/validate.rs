@@ -9,6 +9,7 @@ use oca_rs::Facade;
 use crate::{
     dependency_graph::{parse_name, MutableGraph},
     error::CliError,
+    history::RefnHistory,
     tui::output_window::message_list::{Message, MessageList},
 };
 
@@ -81,6 +82,7 @@ pub fn build(
     graph: &mut MutableGraph,
     infos: Arc<Mutex<MessageList>>,
     cache: &[String],
+    history: Option<&RefnHistory>,
 ) -> Result<Vec<String>, Vec<CliError>> {
     let dependent_nodes = match selected_bundle {
         Some(refn) => {
@@ -106,16 +108,15 @@ pub fn build(
                     .map_err(|e| CliError::ReadFileFailed(path.clone(), e))
                     .unwrap();
                 let (name, _) = parse_name(&path).unwrap();
-                if let Some(name) = name {
-                    if name.ne(&node.refn) {
-                        // Name changed. Update refn in graph
-                        graph.update_refn(&node.refn, name).unwrap();
-                    }
+                let refn = name.unwrap_or_else(|| node.refn.clone());
+                if refn.ne(&node.refn) {
+                    // Name changed. Update refn in graph
+                    graph.update_refn(&node.refn, refn.clone()).unwrap();
                 }
                 let mut f = facade.lock().unwrap();
                 Some(
-                    f.validate_ocafile(unparsed_file)
-                        .map(|ok| (path.clone(), ok))
+                    f.validate_ocafile(unparsed_file.clone())
+                        .map(|ok| (path.clone(), refn, unparsed_file, ok))
                         .map_err(|b| (path.clone(), b)),
                 )
             }
@@ -138,27 +139,19 @@ pub fn build(
     let (_building_oks, building_errs): (Vec<_>, Vec<_>) = oks
         .into_iter()
         .map(|oca_build| {
-            let (path, oca_build) = oca_build.as_ref().unwrap();
+            let (path, refn, unparsed_file, oca_build) = oca_build.as_ref().unwrap();
             let mut f = facade.lock().unwrap();
             match f.build(oca_build) {
                 Ok(oca_bundle) => {
-                    let refs = f.fetch_all_refs().unwrap();
-                    let schema_name = refs
-                        .iter()
-                        .find(|&(_, v)| *v == oca_bundle.said.clone().unwrap().to_string());
-                    let msg = if let Some((refs, _)) = schema_name {
-                        out_cache.push(refs.clone());
-                        format!(
-                            "OCA bundle created in local repository with SAID: {} and name: {}",
-                            oca_bundle.said.unwrap(),
-                            refs
-                        )
-                    } else {
-                        format!(
-                            "OCA bundle created in local repository with SAID: {}",
-                            oca_bundle.said.unwrap()
-                        )
+                    let said = oca_bundle.said.clone().unwrap();
+                    if let Some(history) = history {
+                        history.record_build(refn, &said, path, unparsed_file)?;
                     };
+                    out_cache.push(refn.clone());
+                    let msg = format!(
+                        "OCA bundle created in local repository with SAID: {} and name: {}",
+                        said, refn
+                    );
                     let mut i = infos.lock().unwrap();
                     i.append(Message::Info(msg));
                     Ok(())